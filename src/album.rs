@@ -1,12 +1,15 @@
-use std::time::Duration;
+use std::sync::Arc;
 
-use indicatif::ProgressBar;
 use rand::{seq::SliceRandom, thread_rng};
 use reqwest::Url;
 use snafu::{OptionExt, ResultExt, Snafu};
 use tl::ParserOptions;
 
-use crate::network::create_spoof_client;
+use crate::{
+    endpoint::Endpoints,
+    network::create_spoof_client,
+    progress::{Progress, Transfer},
+};
 
 #[derive(Snafu, Debug)]
 pub enum AlbumError {
@@ -31,6 +34,8 @@ pub enum AlbumError {
     LackOfSrc,
     #[snafu(display("Fails to parse html. Reason: `src` string is not utf8 compatiable"))]
     Utf8Incompatiable,
+    #[snafu(display("Fails to parse html. Reason: Lack of the title of the album"))]
+    LackOfTitle,
     #[snafu(display("Downloaded Html file can not be turned into text"))]
     NotAText { source: reqwest::Error },
 }
@@ -59,67 +64,193 @@ impl Album {
     /// # Example
     ///
     /// ```
-    /// let album = Album::new("https://catbox.moe/c/hpxdlu");
+    /// # use catbox::album::Album;
+    /// # use reqwest::Url;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let album = Album::new(Url::parse("https://catbox.moe/c/hpxdlu")?);
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn new(url: impl Into<Url>) -> Self {
         Self { url: url.into() }
     }
 
+    /// Creates a new `Album` instance from its short, on the server at `endpoints`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::{album::Album, endpoint::Endpoints};
+    /// let album = Album::from_short(&Endpoints::default(), "hpxdlu");
+    /// ```
+    pub fn from_short(endpoints: &Endpoints, short: &str) -> Self {
+        Self::new(endpoints.album(short))
+    }
+
+    /// Gets the short of the album, which is the last part of the url.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::album::Album;
+    /// # use reqwest::Url;
+    /// let album = Album::new(Url::parse("https://catbox.moe/c/hpxdlu")?);
+    /// assert_eq!(album.short(), Some("hpxdlu"));
+    ///
+    /// // servers hosted under a path work the same
+    /// let album = Album::new(Url::parse("http://localhost:8080/catbox/c/hpxdlu")?);
+    /// assert_eq!(album.short(), Some("hpxdlu"));
+    /// # Ok::<(), url::ParseError>(())
+    /// ```
+    pub fn short(&self) -> Option<&str> {
+        self.url.path_segments()?.rfind(|x| !x.is_empty())
+    }
+
     /// Fetches the the URLs from the album's webpage.
     ///
     /// This function sends an HTTP GET request to the album's URL, parses the
     /// HTML response, and extracts the URLs of the files embedded within the page.
+    /// The download of the page is reported to `progress`.
     ///
-    pub async fn fetch_files(&self) -> Result<Files, AlbumError> {
-        let client = create_spoof_client(None).context(ClientCreationSnafu)?;
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// # use catbox::{album::Album, endpoint::Endpoints, progress::{NoProgress, Progress}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let album = Album::from_short(&Endpoints::default(), "hpxdlu");
+    /// let progress: Arc<dyn Progress> = Arc::new(NoProgress);
+    /// let files = album.fetch_files(&progress).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_files(&self, progress: &Arc<dyn Progress>) -> Result<Files, AlbumError> {
+        let file = self.fetch_page(progress).await?;
 
-        let pb = ProgressBar::new_spinner().with_message("Downloading data...");
-        pb.enable_steady_tick(Duration::from_millis(100));
+        let html =
+            tl::parse(&file, ParserOptions::default()).context(HtmlParseSnafu { html: &file })?;
 
-        let file = client
-            .get(self.url.clone())
-            .send()
-            .await
-            .context(RequestSnafu {
-                url: self.url.clone(),
-            })?
-            .error_for_status()
-            .context(ErrorCodeSnafu)?
-            .text()
-            .await
-            .context(NotATextSnafu)?;
+        parse_files(&html)
+    }
 
-        pb.finish_and_clear();
+    /// Fetches the title and description of the album along with its files, from the album's webpage.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// # use catbox::{album::Album, endpoint::Endpoints, progress::{NoProgress, Progress}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let album = Album::from_short(&Endpoints::default(), "hpxdlu");
+    /// let progress: Arc<dyn Progress> = Arc::new(NoProgress);
+    /// let details = album.fetch_details(&progress).await?;
+    /// println!("{}: {}", details.title, details.description);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_details(
+        &self,
+        progress: &Arc<dyn Progress>,
+    ) -> Result<AlbumDetails, AlbumError> {
+        let file = self.fetch_page(progress).await?;
 
         let html =
             tl::parse(&file, ParserOptions::default()).context(HtmlParseSnafu { html: &file })?;
 
         let parser = html.parser();
 
-        let urls = html
-            .get_elements_by_class_name("imagecontainer")
+        let heading = html
+            .get_elements_by_class_name("title")
             .next()
-            .context(LackOfContainerSnafu)?
+            .context(LackOfTitleSnafu)?
             .get(parser)
             .context(LackOfNodeidSnafu)?
             .children()
-            .context(LackOfChildrenSnafu)?
+            .context(LackOfTitleSnafu)?
             .all(parser)
             .iter()
             .filter_map(|x| x.as_tag())
-            .map(|x| {
-                let attrs = x.attributes();
-                attrs
-                    .get("src")
-                    .or_else(|| attrs.get("href"))
-                    .context(LackOfSrcSnafu)
-            })
-            .filter_map(Result::transpose)
-            .map(|x| x?.try_as_utf8_str().context(Utf8IncompatiableSnafu))
-            .map(|x| x.map(|x| Url::parse(x).ok()))
-            .filter(|x| x.as_ref().is_ok_and(Option::is_some))
-            .filter_map(Result::transpose)
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(Files { urls })
+            .map(|x| (x.name().as_utf8_str().into_owned(), x.inner_text(parser)))
+            .collect::<Vec<_>>();
+
+        let text_of = |name: &str| {
+            heading
+                .iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, text)| decode_entities(text.trim()))
+        };
+
+        Ok(AlbumDetails {
+            title: text_of("h2").context(LackOfTitleSnafu)?,
+            // albums without a description have no paragraph at all
+            description: text_of("p").unwrap_or_default(),
+            files: parse_files(&html)?,
+        })
+    }
+
+    async fn fetch_page(&self, progress: &Arc<dyn Progress>) -> Result<String, AlbumError> {
+        let client = create_spoof_client(None).context(ClientCreationSnafu)?;
+
+        let _transfer = Transfer::start(progress, format!("Downloading '{}'", self.url), None);
+
+        client
+            .get(self.url.clone())
+            .send()
+            .await
+            .context(RequestSnafu {
+                url: self.url.clone(),
+            })?
+            .error_for_status()
+            .context(ErrorCodeSnafu)?
+            .text()
+            .await
+            .context(NotATextSnafu)
     }
 }
+
+/// The details shown on the page of an album.
+pub struct AlbumDetails {
+    pub title: String,
+    pub description: String,
+    pub files: Files,
+}
+
+fn parse_files(html: &tl::VDom<'_>) -> Result<Files, AlbumError> {
+    let parser = html.parser();
+
+    let urls = html
+        .get_elements_by_class_name("imagecontainer")
+        .next()
+        .context(LackOfContainerSnafu)?
+        .get(parser)
+        .context(LackOfNodeidSnafu)?
+        .children()
+        .context(LackOfChildrenSnafu)?
+        .all(parser)
+        .iter()
+        .filter_map(|x| x.as_tag())
+        .map(|x| {
+            let attrs = x.attributes();
+            attrs
+                .get("src")
+                .or_else(|| attrs.get("href"))
+                .context(LackOfSrcSnafu)
+        })
+        .filter_map(Result::transpose)
+        .map(|x| x?.try_as_utf8_str().context(Utf8IncompatiableSnafu))
+        .map(|x| x.map(|x| Url::parse(x).ok()))
+        .filter(|x| x.as_ref().is_ok_and(Option::is_some))
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Files { urls })
+}
+
+/// Decodes the entities php's `htmlspecialchars` produces.
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#039;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
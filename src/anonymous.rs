@@ -0,0 +1,145 @@
+use std::{path::Path, sync::Arc};
+
+use reqwest::Url;
+use snafu::{ResultExt, Snafu};
+use tokio::io::AsyncRead;
+
+use crate::{
+    endpoint::Endpoints,
+    network::{ApiClient, ApiError},
+    progress::Progress,
+    rate_limit::RateLimit,
+    retry::{RetryPolicy, Retryable},
+};
+
+#[derive(Snafu, Debug)]
+pub enum AnonymousError {
+    #[snafu(display("Fails to create reqwest client"))]
+    ClientCreation { source: reqwest::Error },
+    #[snafu(transparent)]
+    Api { source: ApiError },
+}
+
+impl Retryable for AnonymousError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::Api { source } if source.is_retryable())
+    }
+}
+
+/// Client that uploads to catbox without an account.
+///
+/// Files uploaded this way can not be listed, deleted or put into albums later.
+#[derive(Clone)]
+pub struct Anonymous {
+    api: ApiClient,
+    endpoints: Endpoints,
+}
+
+impl Anonymous {
+    /// Creates a new `Anonymous` instance talking to the server at `endpoints`, no credentials are needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::{anonymous::Anonymous, endpoint::Endpoints};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let anonymous = Anonymous::new(Endpoints::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(endpoints: Endpoints) -> Result<Self, AnonymousError> {
+        let api = ApiClient::new().context(ClientCreationSnafu)?;
+        Ok(Self { api, endpoints })
+    }
+
+    /// Reports the progress of uploads to `progress`, see `User::with_progress`.
+    #[must_use]
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.api.progress = progress;
+        self
+    }
+
+    /// Sets how failed uploads are retried, see `User::with_retry`.
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.api.retry = retry;
+        self
+    }
+
+    /// Limits how fast files are uploaded, see `User::with_rate_limit`.
+    #[must_use]
+    pub fn with_rate_limit(mut self, rate_limit: impl Into<Option<RateLimit>>) -> Self {
+        self.api.rate_limit = rate_limit.into();
+        self
+    }
+
+    /// Gets the server an `Anonymous` talks to.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Uploads the file anonymously.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{anonymous::Anonymous, endpoint::Endpoints};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let anonymous = Anonymous::new(Endpoints::default())?;
+    /// anonymous.upload_file("./happy.mp4").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_file(
+        &self,
+        path: impl AsRef<Path> + Send,
+    ) -> Result<String, AnonymousError> {
+        Ok(self
+            .api
+            .upload_file(&self.endpoints.api(), &[], path.as_ref())
+            .await?)
+    }
+
+    /// Uploads everything read from `reader` anonymously as a file named `file_name`, without retrying.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::{anonymous::Anonymous, endpoint::Endpoints};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let anonymous = Anonymous::new(Endpoints::default())?;
+    /// let url = anonymous.upload_reader(tokio::io::stdin(), "dump.sql").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_reader(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        file_name: &str,
+    ) -> Result<String, AnonymousError> {
+        Ok(self
+            .api
+            .upload_reader(&self.endpoints.api(), &[], reader, file_name)
+            .await?)
+    }
+
+    /// Uploads a file from a remote url anonymously, the file is fetched by catbox itself.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{anonymous::Anonymous, endpoint::Endpoints};
+    /// # use reqwest::Url;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let anonymous = Anonymous::new(Endpoints::default())?;
+    /// anonymous.upload_url(Url::parse("https://example.com/happy.mp4")?).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_url(&self, url: Url) -> Result<String, AnonymousError> {
+        Ok(self
+            .api
+            .upload_url(&self.endpoints.api(), &[], &url)
+            .await?)
+    }
+}
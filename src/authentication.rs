@@ -1,9 +1,9 @@
-use std::ops::Deref;
+use std::{ops::Deref, sync::Arc};
 
-use reqwest::Client;
+use reqwest::{cookie::Jar, Client, Url};
 use snafu::{ResultExt, Snafu};
 
-use crate::network::create_spoof_client;
+use crate::{endpoint::Endpoints, network::create_spoof_client};
 
 #[derive(Snafu, Debug)]
 pub enum AuthenticationError {
@@ -15,11 +15,18 @@ pub enum AuthenticationError {
     Request { url: String, source: reqwest::Error },
     #[snafu(display("Request returns non 200 error code: '{}'", source.status().map(|x| x.as_u16()).unwrap_or_default()))]
     ErrorCode { source: reqwest::Error },
+    #[snafu(display("Session has expired, logging in again is required"))]
+    SessionExpired,
 }
 
 #[derive(Clone)]
 pub struct AuthenticatedClient {
     client: Client,
+    /// Only read to persist the session, which needs the keyring.
+    #[cfg_attr(not(feature = "keyring"), allow(dead_code))]
+    jar: Arc<Jar>,
+    #[cfg_attr(not(feature = "keyring"), allow(dead_code))]
+    base: Url,
 }
 
 impl Deref for AuthenticatedClient {
@@ -31,32 +38,82 @@ impl Deref for AuthenticatedClient {
 }
 
 impl AuthenticatedClient {
-    pub async fn new(username: &str, password: &str) -> Result<Self, AuthenticationError> {
-        const LOGIN_URL: &str = "https://catbox.moe/user/dologin.php";
+    pub async fn new(
+        endpoints: &Endpoints,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, AuthenticationError> {
+        let login_url = endpoints.login();
 
-        let client = create_spoof_client(None).context(ClientCreationSnafu)?;
+        let jar = Arc::new(Jar::default());
+
+        let client = create_spoof_client(jar.clone()).context(ClientCreationSnafu)?;
 
         client
-            .post(LOGIN_URL)
+            .post(login_url.clone())
             .form(&[("username", username), ("password", password)])
             .send()
             .await
-            .context(RequestSnafu { url: LOGIN_URL })?
+            .context(RequestSnafu { url: login_url })?
             .error_for_status()
             .context(ErrorCodeSnafu)?;
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            jar,
+            base: endpoints.base().clone(),
+        })
+    }
+
+    /// Restores a session from cookies previously returned by `AuthenticatedClient::cookies`.
+    ///
+    /// The session is not checked here, an expired one is reported by `fetch_html`.
+    #[cfg(feature = "keyring")]
+    pub fn from_cookies(endpoints: &Endpoints, cookies: &str) -> Result<Self, AuthenticationError> {
+        let base = endpoints.base().clone();
+
+        let jar = Arc::new(Jar::default());
+
+        for cookie in cookies.split("; ") {
+            jar.add_cookie_str(cookie, &base);
+        }
+
+        let client = create_spoof_client(jar.clone()).context(ClientCreationSnafu)?;
+
+        Ok(Self { client, jar, base })
+    }
+
+    /// Gets the cookies of the session, in the format of a `Cookie` header.
+    #[cfg(feature = "keyring")]
+    pub fn cookies(&self) -> Option<String> {
+        use reqwest::cookie::CookieStore;
+
+        self.jar
+            .cookies(&self.base)
+            .and_then(|x| x.to_str().ok().map(ToOwned::to_owned))
     }
 
-    pub async fn fetch_html(&self, url: &str) -> Result<String, AuthenticationError> {
-        self.client
-            .get(url)
+    /// Fetches the html of a page that requires being logged in.
+    ///
+    /// Catbox sends the login page back when the session has expired,
+    /// which is reported as `AuthenticationError::SessionExpired`.
+    pub async fn fetch_html(&self, url: Url) -> Result<String, AuthenticationError> {
+        let resp = self
+            .client
+            .get(url.clone())
             .send()
             .await
             .context(RequestSnafu { url })?
             .error_for_status()
-            .context(ErrorCodeSnafu)?
-            .text()
-            .await
-            .context(NotATextSnafu)
+            .context(ErrorCodeSnafu)?;
+
+        let redirected_to_login = resp.url().path().ends_with("login.php");
+
+        let html = resp.text().await.context(NotATextSnafu)?;
+
+        if redirected_to_login || html.contains("dologin.php") {
+            return SessionExpiredSnafu.fail();
+        }
+
+        Ok(html)
     }
 }
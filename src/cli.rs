@@ -1,6 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    fmt,
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use argh::FromArgs;
+use catbox::{litterbox::Expiry, upload::UploadSource};
+use reqwest::Url;
+use serde::Deserialize;
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
 /// Top-level command.
@@ -10,6 +18,128 @@ pub struct Cli {
     #[argh(switch, short = 'j')]
     /// whether to output in json
     pub json: bool,
+    #[argh(switch, short = 'a')]
+    /// whether to upload without using the stored credentials
+    pub anonymous: bool,
+    #[argh(option, short = 'p')]
+    /// the account profile to use, defaults to `default`
+    pub profile: Option<String>,
+    #[argh(option)]
+    /// the base url of a catbox-compatible server, defaults to `CBX_BASE_URL` or https://catbox.moe
+    pub base_url: Option<Url>,
+    #[argh(option, default = "ProgressMode::Auto")]
+    /// how progress is reported: auto, bar, json or none, defaults to bars when stderr is a terminal
+    pub progress: ProgressMode,
+    #[argh(option, default = "3")]
+    /// how many times a failed upload is retried on transient errors, defaults to 3
+    pub retries: u32,
+    #[argh(option, default = "500")]
+    /// milliseconds to wait before the first retry, doubled on every following one, defaults to 500
+    pub retry_delay: u64,
+    #[argh(switch)]
+    /// stop a batch at the first file that fails, instead of reporting every failure at the end
+    pub fail_fast: bool,
+    #[argh(option)]
+    /// how many files are uploaded or added to an album at once, defaults to `jobs` in the config file or 5
+    pub jobs: Option<NonZeroUsize>,
+    #[argh(option)]
+    /// the maximum upload speed in bytes per second shared by all uploads, with an optional K, M or G suffix, such as 2M
+    pub limit_rate: Option<ByteSize>,
+}
+
+/// How progress is reported on stderr.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ProgressMode {
+    /// Bars when stderr is a terminal, nothing otherwise.
+    Auto,
+    Bar,
+    /// A line of json for every event.
+    Json,
+    None,
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "bar" => Ok(Self::Bar),
+            "json" => Ok(Self::Json),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "Invalid progress mode `{s}`, expected one of auto, bar, json or none"
+            )),
+        }
+    }
+}
+
+/// The format of `cbx history export`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    /// A json array of records.
+    Json,
+    /// A header row, then a row per record.
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!(
+                "Invalid export format `{s}`, expected either json or csv"
+            )),
+        }
+    }
+}
+
+/// An amount of bytes, with an optional K, M or G suffix in powers of 1024.
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(try_from = "String")]
+pub struct ByteSize(pub NonZeroU64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("Invalid size `{s}`, expected a positive number with an optional K, M or G suffix, such as 2M")
+        };
+
+        let (number, multiplier) = match s.trim().to_ascii_uppercase() {
+            x if x.ends_with('K') => (x[..x.len() - 1].to_owned(), 1 << 10),
+            x if x.ends_with('M') => (x[..x.len() - 1].to_owned(), 1 << 20),
+            x if x.ends_with('G') => (x[..x.len() - 1].to_owned(), 1 << 30),
+            x => (x, 1),
+        };
+
+        let number = number.parse::<f64>().map_err(|_| invalid())?;
+
+        if !number.is_finite() || number < 0.0 {
+            return Err(invalid());
+        }
+
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let bytes = (number * multiplier as f64) as u64;
+
+        NonZeroU64::new(bytes).map(Self).ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
@@ -18,8 +148,75 @@ pub enum CliSubCommands {
     File(FileCommand),
     Album(AlbumCommand),
     Config(ConfigCommand),
+    History(HistoryCommand),
+}
+
+impl CliSubCommands {
+    /// Whether the command transfers files in batches, the only commands the config file applies to.
+    pub const fn is_batch(&self) -> bool {
+        matches!(
+            self,
+            Self::File(FileCommand {
+                command: FileSubCommands::Upload(_) | FileSubCommands::Download(_),
+            }) | Self::Album(AlbumCommand {
+                command: AlbumSubCommands::Add(_)
+                    | AlbumSubCommands::Upload(_)
+                    | AlbumSubCommands::Download(_)
+                    | AlbumSubCommands::Sync(_),
+            })
+        )
+    }
 }
 
+// History Commands <------------------>
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Browsing the local record of every successful upload.
+#[argh(subcommand, name = "history")]
+pub struct HistoryCommand {
+    #[argh(subcommand)]
+    pub command: HistorySubCommands,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+#[argh(subcommand)]
+pub enum HistorySubCommands {
+    List(HistoryList),
+    Search(HistorySearch),
+    Export(HistoryExport),
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Lists past uploads, newest first.
+#[argh(subcommand, name = "list")]
+pub struct HistoryList {
+    #[argh(option, short = 'n')]
+    /// only list the last N uploads
+    pub limit: Option<usize>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Finds past uploads whose path or url contains the query, or whose sha256 starts with it.
+#[argh(subcommand, name = "search")]
+pub struct HistorySearch {
+    #[argh(positional)]
+    /// the text to look for, case insensitive
+    pub query: String,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Exports the whole history, oldest first.
+#[argh(subcommand, name = "export")]
+pub struct HistoryExport {
+    #[argh(option, default = "ExportFormat::Json")]
+    /// the format to export in: json or csv, defaults to json
+    pub format: ExportFormat,
+    #[argh(option, short = 'o')]
+    /// the file to export to, defaults to stdout
+    pub output: Option<PathBuf>,
+}
+
+// <-------------------------------->
 // Config Commands <------------------>
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
@@ -35,22 +232,51 @@ pub struct ConfigCommand {
 pub enum ConfigSubCommands {
     Save(SaveConfig),
     Delete(DeleteConfig),
+    List(ListConfig),
 }
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
-/// Deletes both your account username and password.
+/// Deletes your account username, password and userhash.
 #[argh(subcommand, name = "delete")]
-pub struct DeleteConfig {}
+pub struct DeleteConfig {
+    #[argh(option)]
+    /// the profile to delete, overrides the global `--profile`
+    pub profile: Option<String>,
+}
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
-/// Saves your account username and password.
+/// Lists the saved profiles.
+#[argh(subcommand, name = "list")]
+pub struct ListConfig {}
+
+#[derive(FromArgs, PartialEq, Eq, Clone)]
+/// Saves your account username and password, or your userhash.
+/// only the given values are saved
 #[argh(subcommand, name = "save")]
 pub struct SaveConfig {
+    #[argh(option)]
+    /// the profile to save to, overrides the global `--profile`
+    pub profile: Option<String>,
     #[argh(option)]
     /// your account user name
-    pub username: String,
+    pub username: Option<String>,
     /// your password
     #[argh(option)]
-    pub password: String,
+    pub password: Option<String>,
+    /// your userhash, can be used without a username and password for everything but listing
+    #[argh(option)]
+    pub userhash: Option<String>,
+}
+
+impl fmt::Debug for SaveConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |x: &Option<String>| x.as_ref().map(|_| "<redacted>");
+        f.debug_struct("SaveConfig")
+            .field("profile", &self.profile)
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .field("userhash", &redacted(&self.userhash))
+            .finish()
+    }
 }
 
 // <-------------------------------->
@@ -69,6 +295,8 @@ pub struct FileCommand {
 pub enum FileSubCommands {
     Upload(FileUpload),
     List(FileList),
+    Delete(FileDelete),
+    Download(FileDownload),
 }
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
 /// Uploading files.
@@ -76,12 +304,54 @@ pub enum FileSubCommands {
 pub struct FileList {}
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
-/// Uploading files.
+/// Uploading files, either from disk, from a remote url or from stdin.
 #[argh(subcommand, name = "upload")]
 pub struct FileUpload {
     #[argh(positional)]
-    /// file paths
-    pub paths: Vec<PathBuf>,
+    /// file paths, directories with `--recursive`, http(s) urls, or `-` for stdin
+    pub paths: Vec<UploadSource>,
+    #[argh(option)]
+    /// uploads anonymously to litterbox instead, deleting the files after the given time(1h, 12h, 24h or 72h)
+    pub temporary: Option<Expiry>,
+    #[argh(option)]
+    /// the file name stdin is uploaded as, defaults to `stdin`
+    pub filename: Option<String>,
+    #[argh(switch, short = 'r')]
+    /// upload the files inside directories, skipping hidden and ignored files
+    pub recursive: bool,
+    #[argh(option)]
+    /// only upload files inside directories matching this glob, can be repeated
+    pub include: Vec<String>,
+    #[argh(option)]
+    /// skip files and directories inside directories matching this glob, can be repeated
+    pub exclude: Vec<String>,
+    #[argh(switch)]
+    /// upload files even when an identical file was uploaded before
+    pub force: bool,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Deleting files via their short ids(allows url input).
+#[argh(subcommand, name = "delete")]
+pub struct FileDelete {
+    #[argh(switch, short = 'y')]
+    /// skips the confirmation prompt
+    pub yes: bool,
+    #[argh(positional)]
+    /// files to delete
+    pub files: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Downloading files via their short ids(allows url input), skipping the ones already downloaded.
+#[argh(subcommand, name = "download")]
+pub struct FileDownload {
+    #[argh(option, short = 'o', default = "PathBuf::from(\".\")")]
+    /// the directory to download into, defaults to the current one
+    pub output: PathBuf,
+    #[argh(positional)]
+    /// files to download
+    pub files: Vec<String>,
 }
 
 // <--------------------------------->
@@ -99,7 +369,13 @@ pub struct AlbumCommand {
 pub enum AlbumSubCommands {
     List(AlbumList),
     Add(AddFiles),
+    Remove(RemoveFiles),
     Upload(UploadFiles),
+    Create(CreateAlbum),
+    Edit(EditAlbum),
+    Delete(DeleteAlbum),
+    Download(DownloadAlbum),
+    Sync(SyncAlbum),
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
@@ -115,15 +391,33 @@ pub struct AddFiles {
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
-/// Uploading files via their short ids(allows url input) to said album.
+/// Removing files via their short ids(allows url input) from the album.
+#[argh(subcommand, name = "remove")]
+pub struct RemoveFiles {
+    /// the short of said album(the last part of the url)
+    #[argh(option)]
+    pub album: String,
+    #[argh(positional)]
+    /// files to remove from album
+    pub files: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Uploading files, either from disk, from a remote url or from stdin, to said album.
 #[argh(subcommand, name = "upload")]
 pub struct UploadFiles {
     /// the short of said album(the last part of the url)
     #[argh(option)]
     pub album: String,
     #[argh(positional)]
-    /// files to add to album
-    pub files: Vec<PathBuf>,
+    /// file paths, http(s) urls, or `-` for stdin to add to album
+    pub files: Vec<UploadSource>,
+    #[argh(option)]
+    /// the file name stdin is uploaded as, defaults to `stdin`
+    pub filename: Option<String>,
+    #[argh(switch)]
+    /// upload files even when an identical file was uploaded before
+    pub force: bool,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
@@ -136,4 +430,80 @@ pub struct AlbumList {
     pub album: Option<String>,
 }
 
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Creating an album with files via their short ids(allows url input).
+#[argh(subcommand, name = "create")]
+pub struct CreateAlbum {
+    /// the title of the album
+    #[argh(option)]
+    pub title: String,
+    /// the description of the album
+    #[argh(option, default = "String::new()")]
+    pub description: String,
+    #[argh(positional)]
+    /// files to put in the album
+    pub files: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Editing the title, description and files of an album.
+/// whatever is not given is kept as it currently is
+#[argh(subcommand, name = "edit")]
+pub struct EditAlbum {
+    /// the short of said album(the last part of the url)
+    #[argh(option)]
+    pub album: String,
+    /// the new title of the album
+    #[argh(option)]
+    pub title: Option<String>,
+    /// the new description of the album
+    #[argh(option)]
+    pub description: Option<String>,
+    #[argh(positional)]
+    /// files the album will contain
+    pub files: Vec<String>,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Deleting an album. The files inside are kept.
+#[argh(subcommand, name = "delete")]
+pub struct DeleteAlbum {
+    /// the short of said album(the last part of the url)
+    #[argh(option)]
+    pub album: String,
+    #[argh(switch, short = 'y')]
+    /// skips the confirmation prompt
+    pub yes: bool,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Downloading every file of an album, skipping the ones already downloaded.
+#[argh(subcommand, name = "download")]
+pub struct DownloadAlbum {
+    /// the short of said album(the last part of the url)
+    #[argh(option)]
+    pub album: String,
+    #[argh(option, short = 'o', default = "PathBuf::from(\".\")")]
+    /// the directory to download into, defaults to the current one
+    pub output: PathBuf,
+}
+
+#[derive(FromArgs, PartialEq, Eq, Debug, Clone)]
+/// Making an album match a local directory, uploading new and changed files and adding them to the album.
+#[argh(subcommand, name = "sync")]
+pub struct SyncAlbum {
+    /// the short of said album(the last part of the url)
+    #[argh(option)]
+    pub album: String,
+    #[argh(positional)]
+    /// the directory to publish
+    pub dir: PathBuf,
+    /// remove files without a local counterpart from the album(they are not deleted)
+    #[argh(switch)]
+    pub delete: bool,
+    /// print what would be done without doing it
+    #[argh(switch)]
+    pub dry_run: bool,
+}
+
 // <--------------------------------->
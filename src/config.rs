@@ -0,0 +1,104 @@
+use std::{io, num::NonZeroUsize, path::PathBuf};
+
+use catbox::limits::Limits;
+use serde::Deserialize;
+use snafu::{ResultExt, Snafu};
+
+use crate::cli::ByteSize;
+
+#[derive(Snafu, Debug)]
+pub enum ConfigError {
+    #[snafu(display("Fails to read config file `{}`", path.display()))]
+    Read { path: PathBuf, source: io::Error },
+    #[snafu(display("Config file `{}` is invalid", path.display()))]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+}
+
+/// Settings read from `cbx/config.toml` in the config directory of the platform,
+/// each of them is overridden by its command line option.
+///
+/// ```toml
+/// jobs = 2
+/// limit_rate = "2M"
+///
+/// [limits.litterbox]
+/// max_size = "512M"
+/// blocked_extensions = ["exe", "jar"]
+/// ```
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How many files are uploaded or added to an album at once.
+    pub jobs: Option<NonZeroUsize>,
+    /// The maximum upload speed in bytes per second, shared by all uploads.
+    pub limit_rate: Option<ByteSize>,
+    /// What each server accepts, files breaking these are refused before anything is uploaded.
+    pub limits: LimitsConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub catbox: LimitConfig,
+    pub litterbox: LimitConfig,
+}
+
+impl LimitsConfig {
+    /// The limits of catbox, for uploads to an account and anonymous ones.
+    pub fn catbox(&self) -> Limits {
+        self.catbox.apply(Limits::catbox())
+    }
+
+    pub fn litterbox(&self) -> Limits {
+        self.litterbox.apply(Limits::litterbox())
+    }
+}
+
+/// Overrides of the limits of a server, unset ones keep the defaults of the server.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitConfig {
+    pub max_size: Option<ByteSize>,
+    /// Replaces the blocked extensions, an empty list blocks none.
+    pub blocked_extensions: Option<Vec<String>>,
+}
+
+impl LimitConfig {
+    fn apply(&self, mut limits: Limits) -> Limits {
+        if let Some(max_size) = self.max_size {
+            limits.max_size = Some(max_size.0.get());
+        }
+        if let Some(blocked_extensions) = &self.blocked_extensions {
+            limits.blocked_extensions = blocked_extensions
+                .iter()
+                .map(|x| x.trim_start_matches('.').to_owned())
+                .collect();
+        }
+        limits
+    }
+}
+
+impl Config {
+    /// The location of the config file, `None` when the platform has no config directory.
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("cbx").join("config.toml"))
+    }
+
+    /// Loads the config file, a missing one is the same as an empty one.
+    pub fn load() -> Result<Self, ConfigError> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).context(ReadSnafu { path }),
+        };
+
+        toml::from_str(&text).context(ParseSnafu { path })
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures_util::TryStreamExt;
+use reqwest::{header::CONTENT_LENGTH, Client, Url};
+use snafu::{OptionExt, ResultExt, Snafu};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use crate::{
+    network::create_spoof_client,
+    progress::{NoProgress, Progress, Transfer},
+    retry::{is_retryable_request, is_retryable_status, RetryPolicy, Retryable},
+};
+
+#[derive(Snafu, Debug)]
+pub enum DownloadError {
+    #[snafu(display("Fails to create reqwest client"))]
+    ClientCreation { source: reqwest::Error },
+    #[snafu(display("Request to target failed. url: '{url}'"))]
+    Request { url: Url, source: reqwest::Error },
+    #[snafu(display("Request returns non 200 error code: '{}'", source.status().map(|x| x.as_u16()).unwrap_or_default()))]
+    ErrorCode { source: reqwest::Error },
+    #[snafu(display("Download of '{url}' was interrupted"))]
+    Body { url: Url, source: reqwest::Error },
+    #[snafu(display("Url '{url}' doesn't end with a file name"))]
+    LackOfFileName { url: Url },
+    #[snafu(display("Fails to create directory `{}`", path.display()))]
+    CreateDir { path: PathBuf, source: io::Error },
+    #[snafu(display("Fails to write file `{}`", path.display()))]
+    WriteFile { path: PathBuf, source: io::Error },
+}
+
+impl Retryable for DownloadError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request { source, .. } | Self::Body { source, .. } => {
+                is_retryable_request(source)
+            }
+            Self::ErrorCode { source } => source.status().is_some_and(is_retryable_status),
+            _ => false,
+        }
+    }
+}
+
+/// What happened to a file given to `Downloader::download`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    Downloaded,
+    /// A file of the same size already exists, so it was left alone.
+    Skipped,
+}
+
+/// Client that downloads uploaded files to disk, no credentials are needed.
+#[derive(Clone)]
+pub struct Downloader {
+    client: Client,
+    progress: Arc<dyn Progress>,
+    retry: RetryPolicy,
+}
+
+impl Downloader {
+    /// Creates a new `Downloader` instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::download::Downloader;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let downloader = Downloader::new()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new() -> Result<Self, DownloadError> {
+        let client = create_spoof_client(None).context(ClientCreationSnafu)?;
+        Ok(Self {
+            client,
+            progress: Arc::new(NoProgress),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Reports the progress of downloads to `progress`, nothing is reported by default.
+    #[must_use]
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how failed downloads are retried, `RetryPolicy::default()` is used otherwise.
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Downloads the file at `url` into `dir`, named after the last part of the url.
+    ///
+    /// The file is written next to its destination first and renamed once complete,
+    /// so an interrupted download never leaves a truncated file behind.
+    /// A file of the same size as the download is assumed to be the same file, and is skipped without downloading it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::download::Downloader;
+    /// # use reqwest::Url;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let downloader = Downloader::new()?;
+    /// let (path, outcome) = downloader
+    ///     .download(&Url::parse("https://files.catbox.moe/w0v6bk.webm")?, "./archive".as_ref())
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn download(
+        &self,
+        url: &Url,
+        dir: &Path,
+    ) -> Result<(PathBuf, DownloadOutcome), DownloadError> {
+        let file_name = url
+            .path_segments()
+            .and_then(Iterator::last)
+            .filter(|x| !x.is_empty())
+            .context(LackOfFileNameSnafu { url: url.clone() })?;
+
+        tokio::fs::create_dir_all(dir)
+            .await
+            .context(CreateDirSnafu { path: dir })?;
+
+        let path = dir.join(file_name);
+
+        let outcome = self
+            .retry
+            .run(&*self.progress, url.as_str(), || {
+                self.download_once(url, &path, file_name)
+            })
+            .await?;
+
+        Ok((path, outcome))
+    }
+
+    async fn download_once(
+        &self,
+        url: &Url,
+        path: &Path,
+        file_name: &str,
+    ) -> Result<DownloadOutcome, DownloadError> {
+        if let Ok(metadata) = tokio::fs::metadata(path).await {
+            if self.remote_size(url).await? == Some(metadata.len()) {
+                return Ok(DownloadOutcome::Skipped);
+            }
+        }
+
+        let resp = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .context(RequestSnafu { url: url.clone() })?
+            .error_for_status()
+            .context(ErrorCodeSnafu)?;
+
+        let total = resp.content_length();
+
+        // urls of different servers or paths can share a file name, their downloads must not share a part file
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        let part_path = path.with_file_name(format!(".{file_name}.{:016x}.part", hasher.finish()));
+
+        let result = self.write_body(resp, url, &part_path, total).await;
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&part_path).await;
+        }
+        result?;
+
+        tokio::fs::rename(&part_path, path)
+            .await
+            .context(WriteFileSnafu { path })?;
+
+        Ok(DownloadOutcome::Downloaded)
+    }
+
+    /// The size of the file at `url` according to a `HEAD` request, when the server tells it.
+    async fn remote_size(&self, url: &Url) -> Result<Option<u64>, DownloadError> {
+        let resp = self
+            .client
+            .head(url.clone())
+            .send()
+            .await
+            .context(RequestSnafu { url: url.clone() })?
+            .error_for_status()
+            .context(ErrorCodeSnafu)?;
+
+        // `content_length` is the length of the empty body of a `HEAD` response, not of the file
+        Ok(resp
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|x| x.to_str().ok()?.parse().ok()))
+    }
+
+    /// Streams the body of `resp` into a new file at `path`.
+    async fn write_body(
+        &self,
+        resp: reqwest::Response,
+        url: &Url,
+        path: &Path,
+        total: Option<u64>,
+    ) -> Result<(), DownloadError> {
+        let transfer = Transfer::start(&self.progress, url.to_string(), total);
+
+        let mut file = File::create(path).await.context(WriteFileSnafu { path })?;
+
+        let mut stream = resp.bytes_stream();
+
+        while let Some(chunk) = stream
+            .try_next()
+            .await
+            .context(BodySnafu { url: url.clone() })?
+        {
+            file.write_all(&chunk)
+                .await
+                .context(WriteFileSnafu { path })?;
+            self.progress.sent(transfer.id(), chunk.len() as u64);
+        }
+
+        file.flush().await.context(WriteFileSnafu { path })?;
+        file.sync_all().await.context(WriteFileSnafu { path })
+    }
+}
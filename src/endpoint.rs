@@ -0,0 +1,135 @@
+use reqwest::Url;
+use snafu::{ensure, ResultExt, Snafu};
+
+#[derive(Snafu, Debug)]
+pub enum EndpointError {
+    #[snafu(display("Base url `{url}` can not have paths joined to it"))]
+    CannotBeABase { url: Url },
+    #[snafu(display("Base url `{url}` from `{BASE_URL_ENV}` is not a valid url"))]
+    InvalidBaseUrl {
+        url: String,
+        source: url::ParseError,
+    },
+}
+
+/// Environment variable overriding the default base url.
+pub const BASE_URL_ENV: &str = "CBX_BASE_URL";
+
+const CATBOX_BASE_URL: &str = "https://catbox.moe/";
+const LITTERBOX_API_URL: &str = "https://litterbox.catbox.moe/resources/internals/api.php";
+const FILES_URL: &str = "https://files.catbox.moe/";
+
+/// The urls of a catbox-compatible server.
+///
+/// Every url is derived from a single base url, so pointing `cbx` at a local stand-in
+/// or a self-hosted instance only requires changing the base.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    base: Url,
+    litterbox_api: Url,
+    files: Url,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            base: Url::parse(CATBOX_BASE_URL).expect("catbox url is valid"),
+            litterbox_api: Url::parse(LITTERBOX_API_URL).expect("litterbox url is valid"),
+            files: Url::parse(FILES_URL).expect("files url is valid"),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Creates `Endpoints` for the server at `base`.
+    ///
+    /// Litterbox and the uploaded files live on their own hosts for catbox itself, for any other base
+    /// they are expected to be served under `base`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::endpoint::Endpoints;
+    /// # use reqwest::Url;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let endpoints = Endpoints::new(Url::parse("http://localhost:8080")?)?;
+    /// assert_eq!(endpoints.api().as_str(), "http://localhost:8080/user/api.php");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(mut base: Url) -> Result<Self, EndpointError> {
+        ensure!(
+            !base.cannot_be_a_base(),
+            CannotBeABaseSnafu { url: base.clone() }
+        );
+
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+
+        if base == Self::default().base {
+            return Ok(Self::default());
+        }
+
+        let litterbox_api = join(&base, "resources/internals/api.php");
+
+        Ok(Self {
+            files: base.clone(),
+            base,
+            litterbox_api,
+        })
+    }
+
+    /// Creates `Endpoints` from `CBX_BASE_URL`, falling back to catbox itself when it is unset.
+    pub fn from_env() -> Result<Self, EndpointError> {
+        match std::env::var(BASE_URL_ENV) {
+            Ok(url) if !url.is_empty() => {
+                Self::new(Url::parse(&url).context(InvalidBaseUrlSnafu { url: &url })?)
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn base(&self) -> &Url {
+        &self.base
+    }
+
+    pub fn api(&self) -> Url {
+        join(&self.base, "user/api.php")
+    }
+
+    pub fn login(&self) -> Url {
+        join(&self.base, "user/dologin.php")
+    }
+
+    pub fn account(&self) -> Url {
+        join(&self.base, "user/manage.php")
+    }
+
+    pub fn album_view(&self) -> Url {
+        join(&self.base, "user/manage_albums.php")
+    }
+
+    pub fn user_view(&self) -> Url {
+        join(&self.base, "user/view.php")
+    }
+
+    /// The public page of the album with the given short.
+    pub fn album(&self, short: &str) -> Url {
+        join(&self.base, &format!("c/{short}"))
+    }
+
+    pub fn litterbox_api(&self) -> &Url {
+        &self.litterbox_api
+    }
+
+    /// The url an uploaded file with the given slug is served at.
+    pub fn file(&self, slug: &str) -> Url {
+        join(&self.files, slug)
+    }
+}
+
+fn join(base: &Url, path: &str) -> Url {
+    base.join(path)
+        .expect("base is checked to be joinable in `Endpoints::new`")
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use catbox::{
+    upload::{UploadSource, Uploader},
+    user::UserError,
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use snafu::{ResultExt, Snafu};
+use tokio::io::AsyncReadExt;
+
+use crate::cli::ExportFormat;
+
+#[derive(Snafu, Debug)]
+pub enum HistoryError {
+    #[snafu(display("Fails to read upload history `{}`", path.display()))]
+    Read { path: PathBuf, source: io::Error },
+    #[snafu(display("Upload history `{}` is invalid at line {line}", path.display()))]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[snafu(display("Fails to write upload history `{}`", path.display()))]
+    Write { path: PathBuf, source: io::Error },
+    #[snafu(display("Fails to check the upload history against the uploaded files"))]
+    ListFiles { source: UserError },
+    #[snafu(display("Fails to tell which account the upload history belongs to"))]
+    Account { source: UserError },
+}
+
+/// Where an upload went.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Destination {
+    /// The account of a logged in user.
+    User,
+    Anonymous,
+    Litterbox,
+}
+
+impl From<&Uploader<'_>> for Destination {
+    fn from(uploader: &Uploader<'_>) -> Self {
+        match uploader {
+            Uploader::User(_) => Self::User,
+            Uploader::Anonymous(_) => Self::Anonymous,
+            Uploader::Litterbox(..) => Self::Litterbox,
+        }
+    }
+}
+
+/// A successful upload, one line of json in the history file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Record {
+    /// When the upload finished, in rfc3339 and UTC.
+    pub uploaded_at: String,
+    pub url: String,
+    /// The absolute path, the url or `<stdin>` that was uploaded.
+    pub source: String,
+    /// Size in bytes, only known for paths.
+    pub size: Option<u64>,
+    /// Hex encoded sha256 of the contents, only known for paths.
+    pub sha256: Option<String>,
+    pub destination: Destination,
+    /// Hex encoded sha256 of the user hash of the account uploaded to, so accounts are told apart without storing it.
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+impl Record {
+    /// Records `source` as uploaded to `url` just now, `hash` is the size and sha256 of a path.
+    pub fn new(
+        source: &UploadSource,
+        url: String,
+        hash: Option<(u64, String)>,
+        destination: Destination,
+        account: Option<String>,
+    ) -> Self {
+        let source = match source {
+            UploadSource::Path(path) => std::path::absolute(path)
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string(),
+            other => other.to_string(),
+        };
+        let (size, sha256) = hash.unzip();
+
+        Self {
+            uploaded_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            url,
+            source,
+            size,
+            sha256,
+            destination,
+            account,
+        }
+    }
+
+    /// Whether the upload went to `destination`, and to `account` when it is an account.
+    pub fn is_from(&self, destination: Destination, account: Option<&str>) -> bool {
+        self.destination == destination && self.account.as_deref() == account
+    }
+
+    /// Whether `query` appears in the source or url, or starts the hash, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.source.to_lowercase().contains(&query)
+            || self.url.to_lowercase().contains(&query)
+            || self.sha256.as_ref().is_some_and(|x| x.starts_with(&query))
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}  {}", self.uploaded_at, self.url, self.source)
+    }
+}
+
+/// The location of the history file, `None` when the platform has no data directory.
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("cbx").join("history.jsonl"))
+}
+
+/// Appends `record` to the history file, creating it when needed.
+pub fn append(record: &Record) -> Result<(), HistoryError> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let mut line = serde_json::to_string(record).expect("record is always serializable");
+    line.push('\n');
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context(WriteSnafu { path: &path })?;
+    }
+
+    // a single write per record, so concurrent `cbx` processes don't interleave lines
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .context(WriteSnafu { path })
+}
+
+/// Loads every record, oldest first, a missing history file is the same as an empty one.
+pub fn load() -> Result<Vec<Record>, HistoryError> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).context(ReadSnafu { path }),
+    };
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, x)| !x.trim().is_empty())
+        .map(|(i, x)| {
+            serde_json::from_str(x).context(ParseSnafu {
+                path: &path,
+                line: i + 1,
+            })
+        })
+        .collect()
+}
+
+/// Identifies the account `uploader` uploads to, as recorded in `Record::account`.
+pub async fn account(uploader: &Uploader<'_>) -> Result<Option<String>, HistoryError> {
+    let Uploader::User(user) = uploader else {
+        return Ok(None);
+    };
+
+    let user_hash = user.get_user_hash().await.context(AccountSnafu)?;
+
+    Ok(Some(format!("{:x}", Sha256::digest(user_hash))))
+}
+
+/// Urls of earlier uploads by the sha256 of their contents, for files that `uploader` would upload to the same place.
+///
+/// Litterbox uploads expire, so they are never reused, and uploads to an account are only reused by the same account.
+/// Files uploaded to an account are checked against its uploaded files when it has a login to list them,
+/// leaving out the ones deleted since.
+pub async fn previous_uploads(
+    uploader: &Uploader<'_>,
+) -> Result<HashMap<String, String>, HistoryError> {
+    let (endpoints, destination) = match uploader {
+        Uploader::User(user) => (user.endpoints(), Destination::User),
+        Uploader::Anonymous(anonymous) => (anonymous.endpoints(), Destination::Anonymous),
+        Uploader::Litterbox(..) => return Ok(HashMap::new()),
+    };
+
+    let origin = endpoints.file("").origin();
+
+    let account = account(uploader).await?;
+
+    let mut records = load()?
+        .into_iter()
+        .filter(|x| x.is_from(destination, account.as_deref()))
+        .filter(|x| Url::parse(&x.url).is_ok_and(|x| x.origin() == origin))
+        .collect::<Vec<_>>();
+
+    if records.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // a user hash alone can't list files, the history is trusted as is then
+    if let Uploader::User(user) = uploader {
+        if user.has_login() {
+            let files = user
+                .fetch_uploaded_files()
+                .await
+                .context(ListFilesSnafu)?
+                .into_iter()
+                .collect::<HashSet<_>>();
+            records.retain(|x| Url::parse(&x.url).is_ok_and(|x| files.contains(&x)));
+        }
+    }
+
+    // later uploads win, they are the most likely to still exist
+    Ok(records
+        .into_iter()
+        .filter_map(|x| Some((x.sha256?, x.url)))
+        .collect())
+}
+
+/// The size and hex encoded sha256 of the file at `path`.
+pub async fn hash_file(path: &Path) -> io::Result<(u64, String)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+/// Writes `records` to `writer` in `format`.
+pub fn export(records: &[Record], format: ExportFormat, mut writer: impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, records)?;
+            writeln!(writer)
+        }
+        ExportFormat::Csv => {
+            writeln!(
+                writer,
+                "uploaded_at,url,source,size,sha256,destination,account"
+            )?;
+            for x in records {
+                let destination = serde_json::to_value(x.destination)?;
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    x.uploaded_at,
+                    csv_field(&x.url),
+                    csv_field(&x.source),
+                    x.size.map(|x| x.to_string()).unwrap_or_default(),
+                    x.sha256.as_deref().unwrap_or_default(),
+                    destination.as_str().unwrap_or_default(),
+                    x.account.as_deref().unwrap_or_default(),
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Quotes `field` when it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(source: &str) -> Record {
+        Record {
+            uploaded_at: "2026-10-09T12:00:00Z".to_owned(),
+            url: "https://files.catbox.moe/abc123.png".to_owned(),
+            source: source.to_owned(),
+            size: Some(3),
+            sha256: Some("98ea6e4f216f2fb4".to_owned()),
+            destination: Destination::User,
+            account: Some("5f1e0c4b".to_owned()),
+        }
+    }
+
+    #[test]
+    fn matches_ignoring_case_and_by_hash_prefix() {
+        let record = record("/home/kyle/Report.PDF");
+
+        assert!(record.matches("report.pdf"));
+        assert!(record.matches("ABC123"));
+        assert!(record.matches("98EA6E"));
+        assert!(!record.matches("6e4f"));
+        assert!(!record.matches("invoice"));
+    }
+
+    #[test]
+    fn is_only_from_the_account_it_was_uploaded_to() {
+        let record = record("/tmp/a.png");
+
+        assert!(record.is_from(Destination::User, Some("5f1e0c4b")));
+        assert!(!record.is_from(Destination::User, Some("0d2a9e71")));
+        assert!(!record.is_from(Destination::User, None));
+        assert!(!record.is_from(Destination::Anonymous, None));
+    }
+
+    #[test]
+    fn exports_csv_with_quoted_fields() {
+        let mut output = Vec::new();
+
+        export(
+            &[record("/tmp/a,b.png"), record("/tmp/say \"hi\".png")],
+            ExportFormat::Csv,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "uploaded_at,url,source,size,sha256,destination,account\n\
+             2026-10-09T12:00:00Z,https://files.catbox.moe/abc123.png,\"/tmp/a,b.png\",3,98ea6e4f216f2fb4,user,5f1e0c4b\n\
+             2026-10-09T12:00:00Z,https://files.catbox.moe/abc123.png,\"/tmp/say \"\"hi\"\".png\",3,98ea6e4f216f2fb4,user,5f1e0c4b\n"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! A client library for `catbox.moe`, and the backbone of the `cbx` cli.
+//!
+//! Besides wrapping the api, it also scrapes the website for what the api lacks, such as listing
+//! the files and albums of an account.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use catbox::{endpoint::Endpoints, user::User};
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+//! let url = user.upload_file("./happy.mp4").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Features
+//!
+//! - `keyring`(default): `profile::Profile` and `User::new`, which read credentials from the system keyring
+//!   and cache sessions in it. Without it, a `User` is created with `User::with_login` or `User::with_user_hash`.
+//!
+//! # Progress
+//!
+//! Nothing is drawn by default, progress is reported to a [`progress::Progress`] given to each client:
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use catbox::{endpoint::Endpoints, progress::IndicatifProgress, user::User};
+//! # fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let progress = Arc::new(IndicatifProgress::new(indicatif::MultiProgress::new()));
+//! let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?.with_progress(progress);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod album;
+pub mod anonymous;
+pub(crate) mod authentication;
+pub mod download;
+pub mod endpoint;
+pub mod limits;
+pub mod litterbox;
+pub(crate) mod network;
+#[cfg(feature = "keyring")]
+pub mod profile;
+pub mod progress;
+pub mod rate_limit;
+pub mod retry;
+#[cfg(feature = "keyring")]
+pub(crate) mod session;
+pub mod upload;
+pub mod user;
+
+pub use authentication::AuthenticationError;
+pub use network::ApiError;
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use snafu::Snafu;
+
+/// Why a file can't be uploaded, found before any of it is sent.
+#[derive(Snafu, Debug)]
+pub enum LimitViolation {
+    #[snafu(display("`{}` is {size} bytes, over the limit of {max} bytes", path.display()))]
+    TooLarge { path: PathBuf, size: u64, max: u64 },
+    #[snafu(display("`{}` has the blocked extension `.{extension}`", path.display()))]
+    BlockedExtension { path: PathBuf, extension: String },
+}
+
+/// What a server accepts, so files it would reject are caught before streaming them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// The largest file in bytes, `None` for no limit.
+    pub max_size: Option<u64>,
+    /// Extensions without the leading dot, compared ignoring case.
+    /// A trailing `*` matches every extension starting with the rest, `doc*` blocks `.doc`, `.docx` and `.docm`.
+    pub blocked_extensions: Vec<String>,
+}
+
+impl Limits {
+    /// The largest file catbox accepts.
+    pub const CATBOX_MAX_SIZE: u64 = 200 * 1024 * 1024;
+    /// The largest file litterbox accepts.
+    pub const LITTERBOX_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+    /// Extensions both catbox and litterbox refuse.
+    pub const BLOCKED_EXTENSIONS: &'static [&'static str] = &["exe", "scr", "cpl", "doc*", "jar"];
+
+    /// The limits of `catbox.moe`.
+    pub fn catbox() -> Self {
+        Self {
+            max_size: Some(Self::CATBOX_MAX_SIZE),
+            blocked_extensions: Self::blocked_extensions(),
+        }
+    }
+
+    /// The limits of `litterbox.catbox.moe`.
+    pub fn litterbox() -> Self {
+        Self {
+            max_size: Some(Self::LITTERBOX_MAX_SIZE),
+            blocked_extensions: Self::blocked_extensions(),
+        }
+    }
+
+    fn blocked_extensions() -> Vec<String> {
+        Self::BLOCKED_EXTENSIONS
+            .iter()
+            .map(|&x| x.to_owned())
+            .collect()
+    }
+
+    /// Checks the file at `path` against the limits, returning every rule it breaks.
+    /// Only the metadata of the file is read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::limits::Limits;
+    /// let violations = Limits::catbox().check("./setup.exe".as_ref());
+    /// for x in &violations {
+    ///     eprintln!("{x}");
+    /// }
+    /// ```
+    pub fn check(&self, path: &Path) -> Vec<LimitViolation> {
+        let mut violations = self.check_extension(path).into_iter().collect::<Vec<_>>();
+
+        // a file that can't be read is left to fail its upload, with the error of the client
+        if let (Some(max), Ok(metadata)) = (self.max_size, std::fs::metadata(path)) {
+            if metadata.len() > max {
+                violations.push(LimitViolation::TooLarge {
+                    path: path.to_owned(),
+                    size: metadata.len(),
+                    max,
+                });
+            }
+        }
+
+        violations
+    }
+
+    /// Checks only the extension of `path`, for files that don't exist on disk such as stdin uploaded under a name.
+    pub fn check_extension(&self, path: &Path) -> Option<LimitViolation> {
+        let extension = path.extension()?.to_str()?;
+
+        self.blocked_extensions
+            .iter()
+            .any(|x| match x.strip_suffix('*') {
+                Some(prefix) => extension
+                    .get(..prefix.len())
+                    .is_some_and(|x| x.eq_ignore_ascii_case(prefix)),
+                None => x.eq_ignore_ascii_case(extension),
+            })
+            .then(|| LimitViolation::BlockedExtension {
+                path: path.to_owned(),
+                extension: extension.to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_violation_of_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("limits.JAR");
+        std::fs::write(&path, [0; 16]).unwrap();
+
+        let limits = Limits {
+            max_size: Some(8),
+            ..Limits::catbox()
+        };
+
+        let violations = limits.check(&path);
+
+        assert!(matches!(
+            violations.as_slice(),
+            [
+                LimitViolation::BlockedExtension { extension, .. },
+                LimitViolation::TooLarge { size: 16, max: 8, .. },
+            ] if extension == "JAR"
+        ));
+        assert!(Limits::litterbox()
+            .check_extension("notes.txt".as_ref())
+            .is_none());
+    }
+
+    #[test]
+    fn trailing_star_blocks_extensions_by_prefix() {
+        let limits = Limits::catbox();
+
+        for name in ["a.doc", "a.DOCX", "a.docm", "a.dotx.doc"] {
+            assert!(limits.check_extension(name.as_ref()).is_some(), "{name}");
+        }
+        for name in ["a.do", "a.odt", "a.doc.txt"] {
+            assert!(limits.check_extension(name.as_ref()).is_none(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fmt, path::Path, str::FromStr, sync::Arc};
+
+use snafu::{ResultExt, Snafu};
+use tokio::io::AsyncRead;
+
+use crate::{
+    endpoint::Endpoints,
+    network::{ApiClient, ApiError},
+    progress::Progress,
+    rate_limit::RateLimit,
+    retry::{RetryPolicy, Retryable},
+};
+
+#[derive(Snafu, Debug)]
+pub enum LitterboxError {
+    #[snafu(display("Fails to create reqwest client"))]
+    ClientCreation { source: reqwest::Error },
+    #[snafu(transparent)]
+    Api { source: ApiError },
+}
+
+impl Retryable for LitterboxError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::Api { source } if source.is_retryable())
+    }
+}
+
+#[derive(Snafu, Debug)]
+#[snafu(display("Invalid expiry `{expiry}`, expected one of 1h, 12h, 24h or 72h"))]
+pub struct ParseExpiryError {
+    expiry: String,
+}
+
+/// How long a file uploaded to litterbox lives before being deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    OneHour,
+    TwelveHours,
+    OneDay,
+    ThreeDays,
+}
+
+impl Expiry {
+    /// The value litterbox expects for the `time` field.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::OneHour => "1h",
+            Self::TwelveHours => "12h",
+            Self::OneDay => "24h",
+            Self::ThreeDays => "72h",
+        }
+    }
+}
+
+impl FromStr for Expiry {
+    type Err = ParseExpiryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1h" => Ok(Self::OneHour),
+            "12h" => Ok(Self::TwelveHours),
+            "24h" => Ok(Self::OneDay),
+            "72h" => Ok(Self::ThreeDays),
+            _ => ParseExpirySnafu { expiry: s }.fail(),
+        }
+    }
+}
+
+impl fmt::Display for Expiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Anonymous client for `litterbox.catbox.moe`, catbox's temporary file host.
+#[derive(Clone)]
+pub struct Litterbox {
+    api: ApiClient,
+    endpoints: Endpoints,
+}
+
+impl Litterbox {
+    /// Creates a new `Litterbox` instance talking to the server at `endpoints`, no credentials are needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::{endpoint::Endpoints, litterbox::{Expiry, Litterbox}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let litterbox = Litterbox::new(Endpoints::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(endpoints: Endpoints) -> Result<Self, LitterboxError> {
+        let api = ApiClient::new().context(ClientCreationSnafu)?;
+        Ok(Self { api, endpoints })
+    }
+
+    /// Reports the progress of uploads to `progress`, see `User::with_progress`.
+    #[must_use]
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.api.progress = progress;
+        self
+    }
+
+    /// Sets how failed uploads are retried, see `User::with_retry`.
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.api.retry = retry;
+        self
+    }
+
+    /// Limits how fast files are uploaded, see `User::with_rate_limit`.
+    #[must_use]
+    pub fn with_rate_limit(mut self, rate_limit: impl Into<Option<RateLimit>>) -> Self {
+        self.api.rate_limit = rate_limit.into();
+        self
+    }
+
+    /// Uploads the file to litterbox, it will be deleted after `expiry`.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, litterbox::{Expiry, Litterbox}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let litterbox = Litterbox::new(Endpoints::default())?;
+    /// litterbox.upload_file("./build.log", Expiry::OneDay).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_file(
+        &self,
+        path: impl AsRef<Path> + Send,
+        expiry: Expiry,
+    ) -> Result<String, LitterboxError> {
+        Ok(self
+            .api
+            .upload_file(
+                self.endpoints.litterbox_api(),
+                &[("time", expiry.as_str())],
+                path.as_ref(),
+            )
+            .await?)
+    }
+
+    /// Uploads everything read from `reader` to litterbox as a file named `file_name`, without retrying.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::{endpoint::Endpoints, litterbox::{Expiry, Litterbox}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let litterbox = Litterbox::new(Endpoints::default())?;
+    /// let url = litterbox.upload_reader(tokio::io::stdin(), "dump.sql", Expiry::OneDay).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_reader(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        file_name: &str,
+        expiry: Expiry,
+    ) -> Result<String, LitterboxError> {
+        Ok(self
+            .api
+            .upload_reader(
+                self.endpoints.litterbox_api(),
+                &[("time", expiry.as_str())],
+                reader,
+                file_name,
+            )
+            .await?)
+    }
+}
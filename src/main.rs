@@ -1,126 +1,645 @@
-pub mod album;
-pub(crate) mod authentication;
 mod cli;
-pub(crate) mod network;
-pub mod user;
+mod config;
+mod history;
+mod report;
+mod sync;
+mod walk;
 
 use std::{
-    path::PathBuf,
-    sync::{Arc, LazyLock},
+    collections::HashMap,
+    io::{self, IsTerminal, Write},
+    num::NonZeroUsize,
+    path::Path,
+    process::ExitCode,
+    sync::{Arc, LazyLock, OnceLock},
     time::Duration,
 };
 
 use cli::*;
+use config::Config;
+use history::Destination;
+use report::BatchReport;
+use walk::{expand_directories, WalkOptions};
 
-use album::Album;
-use futures_util::{FutureExt, StreamExt, TryStreamExt};
-use indicatif::{MultiProgress, ProgressBar};
-use keyring::Entry;
+use catbox::{
+    album::Album,
+    anonymous::Anonymous,
+    download::{DownloadOutcome, Downloader},
+    endpoint::Endpoints,
+    limits::Limits,
+    litterbox::Litterbox,
+    profile::Profile,
+    progress::{IndicatifProgress, JsonProgress, NoProgress, Progress, TransferId},
+    rate_limit::RateLimit,
+    retry::RetryPolicy,
+    upload::{UploadError, UploadSource, Uploader},
+    user::{User, UserError},
+};
+use color_eyre::eyre::eyre;
+use futures_util::{FutureExt, StreamExt};
+use indicatif::MultiProgress;
 use reqwest::Url;
 use tokio::sync::OnceCell;
-use user::{User, UserError};
-
-fn get_username_entry() -> keyring::Result<Entry> {
-    Entry::new("catbox-cli", "username")
-}
 
-fn get_password_entry() -> keyring::Result<Entry> {
-    Entry::new("catbox-cli", "password")
-}
+pub static MULTI_PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
 
 pub static USER_INSTANCE: LazyLock<Arc<UserInstance>> =
     LazyLock::new(|| Arc::new(UserInstance::new()));
 
-pub static MULTI_PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
-
 #[derive(Default)]
 pub struct UserInstance {
     cache: OnceCell<User>,
+    profile: OnceLock<Profile>,
+    endpoints: OnceLock<Endpoints>,
+    progress: OnceLock<Arc<dyn Progress>>,
+    retry: OnceLock<RetryPolicy>,
+    rate_limit: OnceLock<Option<RateLimit>>,
 }
 
 impl UserInstance {
     pub fn new() -> Self {
         Self {
             cache: OnceCell::new(),
+            profile: OnceLock::new(),
+            endpoints: OnceLock::new(),
+            progress: OnceLock::new(),
+            retry: OnceLock::new(),
+            rate_limit: OnceLock::new(),
         }
     }
+    /// Sets the profile the `User` is created from, has no effect after the first call.
+    pub fn set_profile(&self, profile: Profile) {
+        let _ = self.profile.set(profile);
+    }
+    /// Sets the server the `User` talks to, has no effect after the first call.
+    pub fn set_endpoints(&self, endpoints: Endpoints) {
+        let _ = self.endpoints.set(endpoints);
+    }
+    /// Sets where progress is reported, has no effect after the first call.
+    pub fn set_progress(&self, progress: Arc<dyn Progress>) {
+        let _ = self.progress.set(progress);
+    }
+    /// Sets how failed uploads are retried, has no effect after the first call.
+    pub fn set_retry(&self, retry: RetryPolicy) {
+        let _ = self.retry.set(retry);
+    }
+    /// Sets how fast files are uploaded, has no effect after the first call.
+    pub fn set_rate_limit(&self, rate_limit: Option<RateLimit>) {
+        let _ = self.rate_limit.set(rate_limit);
+    }
+    /// Uses `user` instead of creating one from the profile, has no effect once a `User` exists.
+    #[cfg(test)]
+    pub fn set_user(&self, user: User) {
+        let _ = self.cache.set(user);
+    }
+    pub fn progress(&self) -> &Arc<dyn Progress> {
+        self.progress.get_or_init(|| Arc::new(NoProgress))
+    }
     pub async fn get(&self) -> Result<&User, UserError> {
-        self.cache.get_or_try_init(User::new).await
+        self.cache
+            .get_or_try_init(|| async {
+                let user = User::new(
+                    self.profile.get_or_init(Profile::default),
+                    self.endpoints.get_or_init(Endpoints::default).clone(),
+                )
+                .await?
+                .with_progress(self.progress().clone())
+                .with_retry(self.retry.get().copied().unwrap_or_default())
+                .with_rate_limit(self.rate_limit.get().cloned().flatten());
+
+                Ok(user)
+            })
+            .await
     }
 }
 
-pub async fn upload_files(paths: impl AsRef<[PathBuf]> + Send) -> color_eyre::Result<Vec<String>> {
-    let user = USER_INSTANCE.get().await?;
+/// How many files of a batch are processed at once when neither `--jobs` nor the config file set it.
+const DEFAULT_JOBS: usize = 5;
 
-    futures_util::stream::iter(paths.as_ref())
-        .map(|x| {
-            user.upload_file(x.clone())
-                .map(move |y| Ok::<_, color_eyre::Report>((x, y?)))
-        })
-        .buffer_unordered(5)
-        .map(|x| {
-            let (path, url) = x?;
-            MULTI_PROGRESS.println(format!("{}: {url}", path.display()))?;
-            Ok(url)
-        })
-        .try_collect::<Vec<_>>()
-        .await
+/// How a batch of files is processed.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    /// How many files are processed at once.
+    pub jobs: usize,
+    /// Whether to stop at the first file that fails.
+    pub fail_fast: bool,
 }
 
-pub async fn add_to_album(album: String, files: Vec<String>) -> color_eyre::Result<()> {
-    let user = USER_INSTANCE.get().await?;
+/// Uploads every source, printing the url of each as soon as it is uploaded and recording it in the upload history.
+/// The uploaded sources are returned along with their urls.
+///
+/// Unless `force` is set, a file identical to one uploaded before is answered with the earlier url instead of being uploaded again.
+/// A failing upload doesn't stop the others unless `fail_fast` is set, the failures are collected into the `BatchReport`.
+pub async fn upload_files(
+    uploader: &Uploader<'_>,
+    sources: impl AsRef<[UploadSource]> + Send,
+    force: bool,
+    options: BatchOptions,
+) -> color_eyre::Result<(Vec<(UploadSource, String)>, BatchReport)> {
+    let sources = sources.as_ref();
 
-    let album = {
-        if album.contains("catbox.moe") {
-            Album::new(Url::parse(&album)?)
-        } else {
-            Album::new(Url::parse(&format!("https://catbox.moe/c/{album}"))?)
+    let mut report = BatchReport::new(sources.len());
+
+    let mut uploaded = Vec::with_capacity(sources.len());
+
+    let destination = Destination::from(uploader);
+
+    // only files are hashed, so there is nothing to look up for urls and stdin
+    let previous = if force || !sources.iter().any(|x| matches!(x, UploadSource::Path(_))) {
+        HashMap::new()
+    } else {
+        // without the history every file is simply uploaded
+        history::previous_uploads(uploader)
+            .await
+            .unwrap_or_else(|err| {
+                MULTI_PROGRESS.suspend(|| eprintln!("{err}, uploading every file"));
+                HashMap::new()
+            })
+    };
+
+    // uploads to an account fail without its user hash, so only failed uploads would miss it here
+    let account = history::account(uploader).await.ok().flatten();
+
+    let mut uploads = futures_util::stream::iter(sources)
+        .map(|x| upload_or_reuse(uploader, x, &previous).map(move |y| (x, y)))
+        .buffer_unordered(options.jobs);
+
+    while let Some((source, (hash, result))) = uploads.next().await {
+        match result {
+            Ok(Uploaded::Reused(url)) => {
+                MULTI_PROGRESS.suspend(|| {
+                    eprintln!("`{source}` was uploaded before, reusing its url");
+                    println!("{source}: {url}");
+                });
+                uploaded.push((source.clone(), url));
+            }
+            Ok(Uploaded::New(url)) => {
+                MULTI_PROGRESS.suspend(|| println!("{source}: {url}"));
+                let record =
+                    history::Record::new(source, url.clone(), hash, destination, account.clone());
+                // the file is uploaded already, so a history that can't be written only warrants a warning
+                if let Err(err) = history::append(&record) {
+                    MULTI_PROGRESS.suspend(|| eprintln!("{err}, `{source}` is not in the history"));
+                }
+                uploaded.push((source.clone(), url));
+            }
+            Err(err) if options.fail_fast => return Err(err.into()),
+            Err(err) => report.fail(source, &err),
         }
+    }
+
+    Ok((uploaded, report))
+}
+
+/// The url a source ended up at.
+enum Uploaded {
+    New(String),
+    /// An identical file was uploaded before.
+    Reused(String),
+}
+
+/// Hashes `source` when it is a path, then either finds it in `previous` or uploads it.
+/// The size and hash are returned too, so the upload can be recorded without reading the file again.
+async fn upload_or_reuse(
+    uploader: &Uploader<'_>,
+    source: &UploadSource,
+    previous: &HashMap<String, String>,
+) -> (Option<(u64, String)>, Result<Uploaded, UploadError>) {
+    let hash = match source {
+        // an unreadable file fails the upload with a better error than hashing would
+        UploadSource::Path(path) => history::hash_file(path).await.ok(),
+        _ => None,
     };
 
-    futures_util::stream::iter(files.into_iter().filter_map(|x| {
-        if x.contains("files.catbox.moe") {
-            Some(Url::parse(&x).ok()?.path_segments()?.next()?.to_owned())
-        } else {
-            Some(x)
+    if let Some(url) = hash.as_ref().and_then(|(_, x)| previous.get(x)) {
+        return (hash, Ok(Uploaded::Reused(url.clone())));
+    }
+
+    let result = uploader.upload(source).await.map(Uploaded::New);
+
+    (hash, result)
+}
+
+/// Checks every file against `limits` before anything is uploaded, failing with all of the violations at once.
+fn preflight(limits: &Limits, sources: &[UploadSource]) -> color_eyre::Result<()> {
+    let violations = sources
+        .iter()
+        .flat_map(|x| match x {
+            UploadSource::Path(path) => limits.check(path),
+            UploadSource::Stdin { file_name } => limits
+                .check_extension(Path::new(file_name))
+                .into_iter()
+                .collect(),
+            // the server fetches urls itself, so their size is unknown up front
+            UploadSource::Url(_) => Vec::new(),
+        })
+        .map(|x| format!("  {x}"))
+        .collect::<Vec<_>>();
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(eyre!(
+        "Nothing was uploaded, the server would reject these files:\n{}",
+        violations.join("\n")
+    ))
+}
+
+/// Names the stdin source after `filename`, refusing to read stdin more than once or to name a stdin that isn't read.
+fn name_stdin(
+    mut sources: Vec<UploadSource>,
+    filename: Option<String>,
+) -> color_eyre::Result<Vec<UploadSource>> {
+    let mut stdin = sources
+        .iter_mut()
+        .filter_map(|x| match x {
+            UploadSource::Stdin { file_name } => Some(file_name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if stdin.len() > 1 {
+        return Err(eyre!(
+            "`-` can only be given once, stdin can only be read once"
+        ));
+    }
+
+    match (stdin.pop(), filename) {
+        (Some(file_name), Some(filename)) => *file_name = filename,
+        (None, Some(_)) => {
+            return Err(eyre!(
+                "`--filename` names the upload read from stdin, pass `-` to read it"
+            ))
         }
-    }))
-    .map(move |x| {
-        let album = album.clone();
+        _ => {}
+    }
 
-        let pb = ProgressBar::new_spinner();
-        MULTI_PROGRESS.add(pb.clone());
+    Ok(sources)
+}
 
-        pb.enable_steady_tick(Duration::from_millis(100));
+/// Downloads every url into `dir`, printing where each file ended up as soon as it is downloaded.
+///
+/// A failing download doesn't stop the others unless `fail_fast` is set, the failures are collected into the `BatchReport`.
+pub async fn download_files(
+    downloader: &Downloader,
+    urls: Vec<Url>,
+    dir: &Path,
+    options: BatchOptions,
+) -> color_eyre::Result<BatchReport> {
+    let mut report = BatchReport::new(urls.len());
 
-        pb.set_message(format!("Uploading '{x}' to album"));
+    let mut downloads = futures_util::stream::iter(&urls)
+        .map(|x| downloader.download(x, dir).map(move |y| (x, y)))
+        .buffer_unordered(options.jobs);
 
-        async move {
-            let x = user.upload_to_album(&album, &x).await;
+    while let Some((url, result)) = downloads.next().await {
+        match result {
+            Ok((path, DownloadOutcome::Downloaded)) => {
+                MULTI_PROGRESS.suspend(|| println!("{url}: {}", path.display()));
+            }
+            Ok((path, DownloadOutcome::Skipped)) => {
+                MULTI_PROGRESS
+                    .suspend(|| println!("{url}: {} (already downloaded)", path.display()));
+            }
+            Err(err) if options.fail_fast => return Err(err.into()),
+            Err(err) => report.fail(url, &err),
+        }
+    }
 
-            pb.finish_and_clear();
+    Ok(report)
+}
 
-            x
+/// Turns either a slug or a file url into the url of the file.
+fn parse_file_url(endpoints: &Endpoints, file: &str) -> Url {
+    match Url::parse(file) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => endpoints.file(file),
+    }
+}
+
+/// Turns either a slug or a file url into a slug.
+fn parse_slug(file: String) -> Option<String> {
+    match Url::parse(&file) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            Some(url.path_segments()?.next_back()?.to_owned())
+        }
+        _ => Some(file),
+    }
+}
+
+/// Turns either an album short or an album url into an `Album`.
+fn parse_album(endpoints: &Endpoints, album: &str) -> Album {
+    match Url::parse(album) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Album::new(url),
+        _ => Album::from_short(endpoints, album),
+    }
+}
+
+/// Prints history records, one per line or as a json array.
+fn print_records(records: &[history::Record], json: bool) -> color_eyre::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(records)?);
+    } else {
+        for x in records {
+            println!("{x}");
         }
-    })
-    .buffer_unordered(5)
-    .try_collect::<Vec<_>>()
-    .await?;
+    }
+    Ok(())
+}
+
+/// Asks the user a yes/no question on the terminal, defaulting to no.
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+pub async fn delete_files(files: Vec<String>, yes: bool) -> color_eyre::Result<()> {
+    let user = USER_INSTANCE.get().await?;
+
+    let slugs = files.into_iter().filter_map(parse_slug).collect::<Vec<_>>();
+
+    if slugs.is_empty() {
+        return Ok(());
+    }
+
+    if !yes && !confirm(&format!("Delete {}?", slugs.join(", ")))? {
+        return Ok(());
+    }
+
+    user.delete_files(&slugs).await?;
+
+    for slug in slugs {
+        println!("Deleted: {slug}");
+    }
+
+    Ok(())
+}
+
+pub async fn remove_from_album(album: String, files: Vec<String>) -> color_eyre::Result<()> {
+    let user = USER_INSTANCE.get().await?;
+
+    let album = parse_album(user.endpoints(), &album);
+
+    let slugs = files.into_iter().filter_map(parse_slug).collect::<Vec<_>>();
+
+    user.remove_from_album(&album, &slugs).await?;
+
+    Ok(())
+}
+
+pub async fn create_album(
+    title: String,
+    description: String,
+    files: Vec<String>,
+) -> color_eyre::Result<Album> {
+    let user = USER_INSTANCE.get().await?;
+
+    let slugs = files.into_iter().filter_map(parse_slug).collect::<Vec<_>>();
+
+    Ok(user.create_album(&title, &description, &slugs).await?)
+}
+
+pub async fn edit_album(
+    album: String,
+    title: Option<String>,
+    description: Option<String>,
+    files: Vec<String>,
+) -> color_eyre::Result<()> {
+    let user = USER_INSTANCE.get().await?;
+
+    let album = parse_album(user.endpoints(), &album);
+
+    // catbox replaces everything on edit, so whatever isn't given is read back from the album
+    let current = if title.is_none() || description.is_none() || files.is_empty() {
+        Some(album.fetch_details(USER_INSTANCE.progress()).await?)
+    } else {
+        None
+    };
+
+    let slugs = match &current {
+        Some(current) if files.is_empty() => current
+            .files
+            .urls
+            .iter()
+            .filter_map(|x| Some(x.path_segments()?.next_back()?.to_owned()))
+            .collect::<Vec<_>>(),
+        _ => files.into_iter().filter_map(parse_slug).collect(),
+    };
+
+    let title = title
+        .or_else(|| current.as_ref().map(|x| x.title.clone()))
+        .unwrap_or_default();
+    let description = description
+        .or_else(|| current.as_ref().map(|x| x.description.clone()))
+        .unwrap_or_default();
+
+    user.edit_album(&album, &title, &description, &slugs)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_album(album: String, yes: bool) -> color_eyre::Result<()> {
+    let user = USER_INSTANCE.get().await?;
+
+    let album = parse_album(user.endpoints(), &album);
+
+    if !yes && !confirm(&format!("Delete album {}?", album.url))? {
+        return Ok(());
+    }
+
+    user.delete_album(&album).await?;
+
     Ok(())
 }
+
+pub async fn add_to_album(
+    album: String,
+    files: Vec<String>,
+    options: BatchOptions,
+) -> color_eyre::Result<BatchReport> {
+    let user = USER_INSTANCE.get().await?;
+
+    let album = parse_album(user.endpoints(), &album);
+
+    let slugs = files.into_iter().filter_map(parse_slug).collect::<Vec<_>>();
+
+    let mut report = BatchReport::new(slugs.len());
+
+    let mut additions = futures_util::stream::iter(slugs)
+        .map(move |x| {
+            let album = album.clone();
+
+            let progress = USER_INSTANCE.progress();
+
+            let name = format!("Adding '{x}' to album");
+
+            let id = TransferId::next();
+
+            progress.started(id, &name, None);
+
+            async move {
+                let result = user.upload_to_album(&album, &x).await;
+
+                progress.finished(id);
+
+                (x, result)
+            }
+        })
+        .buffer_unordered(options.jobs);
+
+    while let Some((slug, result)) = additions.next().await {
+        match result {
+            Err(err) if options.fail_fast => return Err(err.into()),
+            Err(err) => report.fail(slug, &err),
+            Ok(()) => {}
+        }
+    }
+
+    Ok(report)
+}
 /// Album Control
 #[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
 #[allow(clippy::too_many_lines)]
-async fn main() -> color_eyre::Result<()> {
+async fn run() -> color_eyre::Result<ExitCode> {
     color_eyre::install()?;
 
     let cli: Cli = argh::from_env();
 
+    let profile = cli
+        .profile
+        .clone()
+        .map_or_else(Profile::default, Profile::new);
+
+    USER_INSTANCE.set_profile(profile.clone());
+
+    let endpoints = match cli.base_url.clone() {
+        Some(url) => Endpoints::new(url)?,
+        None => Endpoints::from_env()?,
+    };
+
+    USER_INSTANCE.set_endpoints(endpoints.clone());
+
+    let progress: Arc<dyn Progress> = match cli.progress {
+        ProgressMode::Bar => Arc::new(IndicatifProgress::new(MULTI_PROGRESS.clone())),
+        ProgressMode::Auto if io::stderr().is_terminal() => {
+            Arc::new(IndicatifProgress::new(MULTI_PROGRESS.clone()))
+        }
+        ProgressMode::Json => Arc::new(JsonProgress::new(io::stderr())),
+        ProgressMode::Auto | ProgressMode::None => Arc::new(NoProgress),
+    };
+
+    USER_INSTANCE.set_progress(progress.clone());
+
+    let retry = RetryPolicy::new(cli.retries, Duration::from_millis(cli.retry_delay));
+
+    USER_INSTANCE.set_retry(retry);
+
+    // read only where it applies, so a mistake in it doesn't get in the way of listing or fixing things
+    let config = if cli.command.is_batch() {
+        Config::load()?
+    } else {
+        Config::default()
+    };
+
+    let options = BatchOptions {
+        jobs: cli
+            .jobs
+            .or(config.jobs)
+            .map_or(DEFAULT_JOBS, NonZeroUsize::get),
+        fail_fast: cli.fail_fast,
+    };
+
+    let rate_limit = cli
+        .limit_rate
+        .or(config.limit_rate)
+        .map(|x| RateLimit::new(x.0));
+
+    USER_INSTANCE.set_rate_limit(rate_limit.clone());
+
+    let mut code = ExitCode::SUCCESS;
+
     match cli.command {
         CliSubCommands::File(FileCommand {
-            command: FileSubCommands::Upload(FileUpload { paths }),
+            command:
+                FileSubCommands::Upload(FileUpload {
+                    paths,
+                    temporary,
+                    filename,
+                    recursive,
+                    include,
+                    exclude,
+                    force,
+                }),
         }) => {
-            upload_files(paths).await?;
+            let paths = name_stdin(paths, filename)?;
+
+            let paths = expand_directories(
+                paths,
+                &WalkOptions {
+                    recursive,
+                    include,
+                    exclude,
+                },
+            )?;
+
+            let limits = match temporary {
+                Some(_) => config.limits.litterbox(),
+                None => config.limits.catbox(),
+            };
+
+            preflight(&limits, &paths)?;
+
+            let uploader = match temporary {
+                Some(expiry) => Uploader::Litterbox(
+                    Litterbox::new(endpoints)?
+                        .with_progress(progress)
+                        .with_retry(retry)
+                        .with_rate_limit(rate_limit),
+                    expiry,
+                ),
+                None if cli.anonymous => Uploader::Anonymous(
+                    Anonymous::new(endpoints)?
+                        .with_progress(progress)
+                        .with_retry(retry)
+                        .with_rate_limit(rate_limit),
+                ),
+                None => match USER_INSTANCE.get().await {
+                    Ok(user) => Uploader::User(user),
+                    Err(err) if err.is_missing_credentials() => {
+                        MULTI_PROGRESS.suspend(|| {
+                            eprintln!("No credentials are configured, uploading anonymously.");
+                        });
+                        Uploader::Anonymous(
+                            Anonymous::new(endpoints)?
+                                .with_progress(progress)
+                                .with_retry(retry)
+                                .with_rate_limit(rate_limit),
+                        )
+                    }
+                    Err(err) => return Err(err.into()),
+                },
+            };
+
+            let (_, report) = upload_files(&uploader, paths, force, options).await?;
+
+            code = report.finish()?;
         }
         CliSubCommands::File(FileCommand {
             command: FileSubCommands::List(FileList {}),
@@ -136,30 +655,129 @@ async fn main() -> color_eyre::Result<()> {
                 }
             }
         }
+        CliSubCommands::File(FileCommand {
+            command: FileSubCommands::Delete(FileDelete { yes, files }),
+        }) => {
+            delete_files(files, yes).await?;
+        }
+        CliSubCommands::File(FileCommand {
+            command: FileSubCommands::Download(FileDownload { output, files }),
+        }) => {
+            let downloader = Downloader::new()?.with_progress(progress).with_retry(retry);
+
+            let urls = files
+                .iter()
+                .map(|x| parse_file_url(&endpoints, x))
+                .collect();
+
+            code = download_files(&downloader, urls, &output, options)
+                .await?
+                .finish()?;
+        }
         CliSubCommands::Album(AlbumCommand {
             command: AlbumSubCommands::Add(AddFiles { album, files }),
         }) => {
-            add_to_album(album, files).await?;
+            code = add_to_album(album, files, options).await?.finish()?;
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command: AlbumSubCommands::Remove(RemoveFiles { album, files }),
+        }) => {
+            remove_from_album(album, files).await?;
         }
         CliSubCommands::Album(AlbumCommand {
-            command: AlbumSubCommands::Upload(UploadFiles { album, files }),
+            command:
+                AlbumSubCommands::Upload(UploadFiles {
+                    album,
+                    files,
+                    filename,
+                    force,
+                }),
         }) => {
-            let urls = upload_files(files).await?;
+            let files = name_stdin(files, filename)?;
+
+            preflight(&config.limits.catbox(), &files)?;
+
+            let uploader = Uploader::User(USER_INSTANCE.get().await?);
+
+            let (uploaded, mut report) = upload_files(&uploader, files, force, options).await?;
+
+            let urls = uploaded.into_iter().map(|(_, url)| url).collect();
 
-            add_to_album(album, urls).await?;
+            report.merge(add_to_album(album, urls, options).await?);
+
+            code = report.finish()?;
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command:
+                AlbumSubCommands::Create(CreateAlbum {
+                    title,
+                    description,
+                    files,
+                }),
+        }) => {
+            let album = create_album(title, description, files).await?;
+
+            if cli.json {
+                println!("{}", serde_json::to_string_pretty(&album.url)?);
+            } else {
+                println!("{}", album.url);
+            }
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command:
+                AlbumSubCommands::Edit(EditAlbum {
+                    album,
+                    title,
+                    description,
+                    files,
+                }),
+        }) => {
+            edit_album(album, title, description, files).await?;
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command: AlbumSubCommands::Delete(DeleteAlbum { album, yes }),
+        }) => {
+            delete_album(album, yes).await?;
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command: AlbumSubCommands::Download(DownloadAlbum { album, output }),
+        }) => {
+            let album = parse_album(&endpoints, &album);
+
+            let urls = album.fetch_files(&progress).await?.urls;
+
+            let downloader = Downloader::new()?.with_progress(progress).with_retry(retry);
+
+            code = download_files(&downloader, urls, &output, options)
+                .await?
+                .finish()?;
+        }
+        CliSubCommands::Album(AlbumCommand {
+            command:
+                AlbumSubCommands::Sync(SyncAlbum {
+                    album,
+                    dir,
+                    delete,
+                    dry_run,
+                }),
+        }) => {
+            code = sync::sync_album(
+                album,
+                dir,
+                delete,
+                dry_run,
+                &config.limits.catbox(),
+                options,
+            )
+            .await?
+            .finish()?;
         }
         CliSubCommands::Album(AlbumCommand {
             command: AlbumSubCommands::List(AlbumList { album: Some(album) }),
         }) => {
-            let album = {
-                if album.contains("catbox.moe") {
-                    Album::new(Url::parse(&album)?)
-                } else {
-                    Album::new(Url::parse(&format!("https://catbox.moe/c/{album}"))?)
-                }
-            };
+            let album = parse_album(&endpoints, &album);
 
-            let files = album.fetch_files().await?.urls;
+            let files = album.fetch_files(&progress).await?.urls;
 
             if cli.json {
                 println!("{}", serde_json::to_string_pretty(&files)?);
@@ -185,19 +803,98 @@ async fn main() -> color_eyre::Result<()> {
                 }
             }
         }
+        CliSubCommands::History(HistoryCommand {
+            command: HistorySubCommands::List(HistoryList { limit }),
+        }) => {
+            let mut records = history::load()?;
+            records.reverse();
+            records.truncate(limit.unwrap_or(usize::MAX));
+
+            print_records(&records, cli.json)?;
+        }
+        CliSubCommands::History(HistoryCommand {
+            command: HistorySubCommands::Search(HistorySearch { query }),
+        }) => {
+            let mut records = history::load()?;
+            records.retain(|x| x.matches(&query));
+            records.reverse();
+
+            print_records(&records, cli.json)?;
+        }
+        CliSubCommands::History(HistoryCommand {
+            command: HistorySubCommands::Export(HistoryExport { format, output }),
+        }) => {
+            let records = history::load()?;
+
+            match output {
+                Some(output) => {
+                    let file = std::fs::File::create(&output)?;
+                    history::export(&records, format, io::BufWriter::new(file))?;
+                }
+                None => history::export(&records, format, io::stdout().lock())?,
+            }
+        }
         CliSubCommands::Config(ConfigCommand {
-            command: ConfigSubCommands::Save(SaveConfig { username, password }),
+            command:
+                ConfigSubCommands::Save(SaveConfig {
+                    profile: save_profile,
+                    username,
+                    password,
+                    userhash,
+                }),
         }) => {
-            get_username_entry()?.set_password(&username)?;
-            get_password_entry()?.set_password(&password)?;
+            let profile = save_profile.map_or(profile, Profile::new);
+
+            if username.is_some() || password.is_some() {
+                profile.clear_session();
+            }
+            if let Some(username) = username {
+                profile.username_entry()?.set_password(&username)?;
+            }
+            if let Some(password) = password {
+                profile.password_entry()?.set_password(&password)?;
+            }
+            if let Some(userhash) = userhash {
+                profile.userhash_entry()?.set_password(&userhash)?;
+            }
+            profile.register()?;
+        }
+        CliSubCommands::Config(ConfigCommand {
+            command:
+                ConfigSubCommands::Delete(DeleteConfig {
+                    profile: delete_profile,
+                }),
+        }) => {
+            delete_profile.map_or(profile, Profile::new).delete()?;
         }
         CliSubCommands::Config(ConfigCommand {
-            command: ConfigSubCommands::Delete(DeleteConfig {}),
+            command: ConfigSubCommands::List(ListConfig {}),
         }) => {
-            get_username_entry()?.delete_credential()?;
-            get_password_entry()?.delete_credential()?;
+            let profiles = Profile::list()?;
+
+            if cli.json {
+                let profiles = profiles
+                    .iter()
+                    .map(|x| {
+                        serde_json::json!({
+                            "name": x.name(),
+                            "username": x.username(),
+                            "userhash": x.has_userhash(),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&profiles)?);
+            } else {
+                for x in profiles {
+                    match (x.username(), x.has_userhash()) {
+                        (Some(username), _) => println!("{x}: {username}"),
+                        (None, true) => println!("{x}: (userhash only)"),
+                        (None, false) => println!("{x}: (not configured)"),
+                    }
+                }
+            }
         }
     }
 
-    Ok(())
+    Ok(code)
 }
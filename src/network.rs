@@ -1,11 +1,199 @@
-use reqwest::{Client, ClientBuilder};
-use std::sync::Arc;
+use futures_util::TryStreamExt;
+use reqwest::{
+    multipart::{Form, Part},
+    Body, Client, ClientBuilder, Response, StatusCode, Url,
+};
+use snafu::{ResultExt, Snafu};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{fs::File, io::AsyncRead};
+use tokio_util::codec::{BytesCodec, FramedRead};
 
 use reqwest::{
     cookie::{self},
     header,
 };
 
+use crate::{
+    progress::{NoProgress, Progress, Transfer},
+    rate_limit::RateLimit,
+    retry::{is_retryable_request, is_retryable_status, RetryPolicy, Retryable},
+};
+
+/// A failed call to the upload api, the same for every client.
+#[derive(Snafu, Debug)]
+pub enum ApiError {
+    #[snafu(display("Request to target failed. url: '{url}'"))]
+    Request { url: String, source: reqwest::Error },
+    #[snafu(display("Return to response can't be parsed as text"))]
+    NotAText { source: reqwest::Error },
+    #[snafu(display("Request returns non 200 error code: '{}'!\nserver reason: {reason}", code.as_u16()))]
+    ErrorCode { reason: String, code: StatusCode },
+    #[snafu(display("Fails to read file `{}`", file.display()))]
+    ReadFile { file: PathBuf, source: io::Error },
+}
+
+impl Retryable for ApiError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request { source, .. } | Self::NotAText { source } => {
+                is_retryable_request(source)
+            }
+            Self::ErrorCode { code, .. } => is_retryable_status(*code),
+            Self::ReadFile { .. } => false,
+        }
+    }
+}
+
+/// The upload core shared by `User`, `Anonymous` and `Litterbox`.
+///
+/// Each of them only adds the api it talks to and its own form fields, such as the userhash or the expiry.
+#[derive(Clone)]
+pub(crate) struct ApiClient {
+    client: Client,
+    pub(crate) progress: Arc<dyn Progress>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limit: Option<RateLimit>,
+}
+
+impl ApiClient {
+    pub(crate) fn new() -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: create_spoof_client(None)?,
+            progress: Arc::new(NoProgress),
+            retry: RetryPolicy::default(),
+            rate_limit: None,
+        })
+    }
+
+    /// Uploads the file at `path` to `api` along with `fields`.
+    ///
+    /// The file is opened again on every attempt, so failed uploads are retried according to `retry`.
+    pub(crate) async fn upload_file(
+        &self,
+        api: &Url,
+        fields: &[(&'static str, &str)],
+        path: &Path,
+    ) -> Result<String, ApiError> {
+        let name = path.to_string_lossy();
+
+        self.retry
+            .run(&*self.progress, &name, || async {
+                let (part, _transfer) =
+                    progress_file_part(path, &self.progress, self.rate_limit.as_ref())
+                        .await
+                        .context(ReadFileSnafu { file: path })?;
+
+                self.send_part(api, fields, part, &name).await
+            })
+            .await
+    }
+
+    /// Uploads everything read from `reader` to `api` as a file named `file_name`, along with `fields`.
+    ///
+    /// The length is unknown up front so the upload is streamed, and it is never retried since `reader` can't be read again.
+    pub(crate) async fn upload_reader(
+        &self,
+        api: &Url,
+        fields: &[(&'static str, &str)],
+        reader: impl AsyncRead + Send + Sync + 'static,
+        file_name: &str,
+    ) -> Result<String, ApiError> {
+        let (part, _transfer) = progress_reader_part(
+            reader,
+            file_name.to_owned(),
+            None,
+            &self.progress,
+            self.rate_limit.as_ref(),
+        );
+
+        self.send_part(api, fields, part, file_name).await
+    }
+
+    /// Has the server at `api` fetch and upload the file at `url` itself, along with `fields`.
+    pub(crate) async fn upload_url(
+        &self,
+        api: &Url,
+        fields: &[(&'static str, &str)],
+        url: &Url,
+    ) -> Result<String, ApiError> {
+        let _transfer = Transfer::start(&self.progress, format!("Uploading '{url}'"), None);
+
+        let form = [("reqtype", "urlupload"), ("url", url.as_str())]
+            .into_iter()
+            .chain(fields.iter().copied())
+            .collect::<Vec<_>>();
+
+        self.retry
+            .run(&*self.progress, url.as_str(), || self.post_form(api, &form))
+            .await
+    }
+
+    /// Posts `form` to `api` once, returning the response text on success.
+    pub(crate) async fn post_form(
+        &self,
+        api: &Url,
+        form: &[(&str, &str)],
+    ) -> Result<String, ApiError> {
+        let resp = self
+            .client
+            .post(api.clone())
+            .form(form)
+            .send()
+            .await
+            .context(RequestSnafu { url: api.as_str() })?;
+
+        read_response(resp).await
+    }
+
+    /// Sends `part` as the file of an upload, `name` only shows up in errors.
+    async fn send_part(
+        &self,
+        api: &Url,
+        fields: &[(&'static str, &str)],
+        part: Part,
+        name: &str,
+    ) -> Result<String, ApiError> {
+        let form = fields
+            .iter()
+            .fold(
+                Form::new().text("reqtype", "fileupload"),
+                |form, &(key, value)| form.text(key, value.to_owned()),
+            )
+            .part("fileToUpload", part);
+
+        let resp = self
+            .client
+            .post(api.clone())
+            .multipart(form)
+            .send()
+            .await
+            .context(RequestSnafu { url: name })?;
+
+        read_response(resp).await
+    }
+}
+
+/// The text of `resp`, or the reason the server gave for failing.
+async fn read_response(resp: Response) -> Result<String, ApiError> {
+    let code = resp.status();
+
+    let text = resp.text().await.context(NotATextSnafu)?;
+
+    if !code.is_success() {
+        ErrorCodeSnafu {
+            code,
+            reason: &text,
+        }
+        .fail()?;
+    }
+
+    Ok(text)
+}
+
 pub fn create_spoof_client(
     cookie_provider: impl Into<Option<Arc<cookie::Jar>>>,
 ) -> Result<Client, reqwest::Error> {
@@ -27,15 +215,75 @@ pub fn create_spoof_client(
                 header::HeaderValue::from_static("en-US,en;q=0.9"),
             )
         ]);
-    let mut builder = ClientBuilder::new();
+    let builder = match cookie_provider.into() {
+        Some(provider) => ClientBuilder::new().cookie_provider(provider),
+        None => ClientBuilder::new().cookie_store(true),
+    };
 
-    if let Some(provider) = cookie_provider.into() {
-        builder = builder.cookie_provider(provider);
-    }
+    builder.no_proxy().default_headers(headers).build()
+}
+
+/// Opens the file at `path` as a streamed multipart part.
+///
+/// The bytes sent are reported to `progress` under the path of the file,
+/// the transfer is finished once the returned `Transfer` is dropped.
+/// Every chunk waits for `rate_limit` before being sent.
+async fn progress_file_part(
+    path: &Path,
+    progress: &Arc<dyn Progress>,
+    rate_limit: Option<&RateLimit>,
+) -> io::Result<(Part, Transfer)> {
+    let file = File::open(path).await?;
+
+    let total_bytes = file.metadata().await?.len();
+
+    let name = path.to_string_lossy().to_string();
+
+    Ok(progress_reader_part(
+        file,
+        name,
+        Some(total_bytes),
+        progress,
+        rate_limit,
+    ))
+}
+
+/// Streams everything read from `reader` as a multipart part named `name`.
+///
+/// Without a `total`, the part is sent without a length and the transfer is reported as indeterminate.
+fn progress_reader_part(
+    reader: impl AsyncRead + Send + Sync + 'static,
+    name: String,
+    total: Option<u64>,
+    progress: &Arc<dyn Progress>,
+    rate_limit: Option<&RateLimit>,
+) -> (Part, Transfer) {
+    let transfer = Transfer::start(progress, name.clone(), total);
+
+    let progress = progress.clone();
+    let id = transfer.id();
+    let rate_limit = rate_limit.cloned();
+
+    let stream = FramedRead::new(reader, BytesCodec::new())
+        .and_then(move |x| {
+            let rate_limit = rate_limit.clone();
+            async move {
+                if let Some(rate_limit) = rate_limit {
+                    rate_limit.acquire(x.len() as u64).await;
+                }
+                Ok(x)
+            }
+        })
+        .inspect_ok(move |x| {
+            progress.sent(id, x.len() as u64);
+        });
+
+    let body = Body::wrap_stream(stream);
+
+    let part = match total {
+        Some(total) => Part::stream_with_length(body, total),
+        None => Part::stream(body),
+    };
 
-    builder
-        .no_proxy()
-        .default_headers(headers)
-        .cookie_store(true)
-        .build()
+    (part.file_name(name), transfer)
 }
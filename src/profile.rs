@@ -0,0 +1,166 @@
+use std::fmt;
+
+use keyring::Entry;
+
+use crate::session::SessionCache;
+
+const SERVICE: &str = "catbox-cli";
+
+/// A named set of credentials stored in the keyring, allowing a machine to hold multiple accounts.
+///
+/// The default profile uses the same keyring entries `catbox-cli` has always used,
+/// other profiles prefix the entries with their name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Profile {
+    name: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl Profile {
+    pub const DEFAULT: &'static str = "default";
+
+    /// Creates a new `Profile`, nothing is stored until one of its entries is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::profile::Profile;
+    /// let profile = Profile::new("work");
+    /// ```
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == Self::DEFAULT
+    }
+
+    fn entry(&self, key: &str) -> keyring::Result<Entry> {
+        if self.is_default() {
+            Entry::new(SERVICE, key)
+        } else {
+            Entry::new(SERVICE, &format!("{}/{key}", self.name))
+        }
+    }
+
+    pub fn username_entry(&self) -> keyring::Result<Entry> {
+        self.entry("username")
+    }
+
+    pub fn password_entry(&self) -> keyring::Result<Entry> {
+        self.entry("password")
+    }
+
+    pub fn userhash_entry(&self) -> keyring::Result<Entry> {
+        self.entry("userhash")
+    }
+
+    pub fn session_entry(&self) -> keyring::Result<Entry> {
+        self.entry("session")
+    }
+
+    /// Gets the username stored in the profile, if any.
+    pub fn username(&self) -> Option<String> {
+        self.username_entry().ok()?.get_password().ok()
+    }
+
+    /// Whether a userhash is stored in the profile.
+    pub fn has_userhash(&self) -> bool {
+        self.userhash_entry().and_then(|x| x.get_password()).is_ok()
+    }
+
+    /// Lists the saved profiles, the default profile always comes first.
+    ///
+    /// The keyring can't be enumerated, so the names of non-default profiles are kept in an entry of their own.
+    pub fn list() -> keyring::Result<Vec<Self>> {
+        let names = match profiles_entry()?.get_password() {
+            Ok(names) => names,
+            Err(keyring::Error::NoEntry) => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(std::iter::once(Self::default())
+            .chain(names.lines().filter(|x| !x.is_empty()).map(Self::new))
+            .collect())
+    }
+
+    /// Records the profile in the list returned by `Profile::list`.
+    pub fn register(&self) -> keyring::Result<()> {
+        let mut profiles = Self::list()?;
+
+        if self.is_default() || profiles.contains(self) {
+            return Ok(());
+        }
+
+        profiles.push(self.clone());
+
+        store_profiles(&profiles)
+    }
+
+    /// Removes the profile from the list returned by `Profile::list`.
+    pub fn unregister(&self) -> keyring::Result<()> {
+        let mut profiles = Self::list()?;
+
+        profiles.retain(|x| x != self);
+
+        store_profiles(&profiles)
+    }
+
+    /// Removes the cached session of the profile, forcing the next `User` to log in again.
+    pub fn clear_session(&self) {
+        SessionCache::clear(self);
+    }
+
+    /// Deletes every entry stored in the profile, entries that don't exist are skipped.
+    pub fn delete(&self) -> keyring::Result<()> {
+        for entry in [
+            self.username_entry()?,
+            self.password_entry()?,
+            self.userhash_entry()?,
+            self.session_entry()?,
+        ] {
+            match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        self.unregister()
+    }
+}
+
+fn profiles_entry() -> keyring::Result<Entry> {
+    Entry::new(SERVICE, "profiles")
+}
+
+fn store_profiles(profiles: &[Profile]) -> keyring::Result<()> {
+    let names = profiles
+        .iter()
+        .filter(|x| !x.is_default())
+        .map(Profile::name)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if names.is_empty() {
+        return match profiles_entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err),
+        };
+    }
+
+    profiles_entry()?.set_password(&names)
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// Identifies a single transfer, names are not unique since the same file or url can be transferred twice at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransferId(u64);
+
+impl TransferId {
+    /// A new id, different from every other one created by the process.
+    pub fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Observer of the progress of uploads and fetches.
+///
+/// Every transfer has an `id` and a `name`, which is the path or url being transferred,
+/// or a description of the work for transfers without a meaningful size.
+pub trait Progress: Send + Sync {
+    /// A transfer has started, `total` is its size in bytes when it is known up front.
+    fn started(&self, id: TransferId, name: &str, total: Option<u64>);
+    /// `bytes` more bytes of a transfer have been sent.
+    fn sent(&self, id: TransferId, bytes: u64);
+    /// A transfer has finished, whether it succeeded or not.
+    fn finished(&self, id: TransferId);
+    /// A status message that is not tied to any transfer.
+    fn message(&self, message: &str);
+}
+
+/// Reports nothing, the default for every client of the library.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn started(&self, _id: TransferId, _name: &str, _total: Option<u64>) {}
+    fn sent(&self, _id: TransferId, _bytes: u64) {}
+    fn finished(&self, _id: TransferId) {}
+    fn message(&self, _message: &str) {}
+}
+
+/// Draws a progress bar for every transfer, or a spinner when the size is unknown.
+pub struct IndicatifProgress {
+    multi: MultiProgress,
+    bars: Mutex<HashMap<TransferId, ProgressBar>>,
+}
+
+impl IndicatifProgress {
+    /// Creates a new `IndicatifProgress` drawing its bars through `multi`.
+    pub fn new(multi: MultiProgress) -> Self {
+        Self {
+            multi,
+            bars: Mutex::default(),
+        }
+    }
+}
+
+impl Progress for IndicatifProgress {
+    /// # Panics
+    ///
+    /// Panics when the template provided to `ProgressBar` is invalid(compile time mistake)
+    fn started(&self, id: TransferId, name: &str, total: Option<u64>) {
+        let bar = match total {
+            Some(total) => {
+                let bar = ProgressBar::new(total).with_prefix(name.to_owned());
+                bar.set_style(
+                    ProgressStyle::with_template(
+                        "{prefix:.magenta}\n[ETA: {eta}] [{decimal_bytes_per_sec:}] [{elapsed_precise}] {wide_bar:.cyan/blue} {decimal_bytes}/{decimal_total_bytes}",
+                    )
+                    .expect("Invalid template(compile time issue)")
+                    .progress_chars("##-"),
+                );
+                bar
+            }
+            None => ProgressBar::new_spinner().with_message(name.to_owned()),
+        };
+
+        self.multi.add(bar.clone());
+
+        bar.enable_steady_tick(Duration::from_millis(100));
+
+        lock(&self.bars).insert(id, bar);
+    }
+
+    /// # Panics
+    ///
+    /// Panics when the template provided to `ProgressBar` is invalid(compile time mistake)
+    fn sent(&self, id: TransferId, bytes: u64) {
+        if let Some(bar) = lock(&self.bars).get(&id) {
+            // bytes are only shown once some are sent, so spinners waiting on a response(like logging in) don't show `0 B`
+            if bar.length().is_none() && bar.position() == 0 {
+                bar.set_style(
+                    ProgressStyle::with_template(
+                        "{spinner} {msg} [{decimal_bytes_per_sec:}] [{elapsed_precise}] {decimal_bytes}",
+                    )
+                    .expect("Invalid template(compile time issue)"),
+                );
+            }
+            bar.inc(bytes);
+        }
+    }
+
+    fn finished(&self, id: TransferId) {
+        if let Some(bar) = lock(&self.bars).remove(&id) {
+            bar.finish_and_clear();
+        }
+    }
+
+    fn message(&self, message: &str) {
+        let _ = self.multi.println(message);
+    }
+}
+
+/// Writes every event as a line of json, for consumption by other programs.
+///
+/// `sent` is written at most every `JsonProgress::SENT_INTERVAL` per transfer, and once more when the last byte is sent.
+///
+/// ```text
+/// {"event":"started","id":0,"name":"./happy.mp4","total":1048576}
+/// {"event":"sent","id":0,"name":"./happy.mp4","sent":8192,"total":1048576}
+/// {"event":"finished","id":0,"name":"./happy.mp4"}
+/// {"event":"message","message":"..."}
+/// ```
+pub struct JsonProgress<W> {
+    writer: Mutex<W>,
+    transfers: Mutex<HashMap<TransferId, JsonTransfer>>,
+}
+
+struct JsonTransfer {
+    name: String,
+    sent: u64,
+    total: Option<u64>,
+    /// When `sent` was last written, `None` before the first time.
+    written: Option<Instant>,
+}
+
+impl<W: Write + Send> JsonProgress<W> {
+    /// The least time between two `sent` events of the same transfer.
+    pub const SENT_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Creates a new `JsonProgress` writing its events to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            transfers: Mutex::default(),
+        }
+    }
+
+    fn emit(&self, event: &serde_json::Value) {
+        let mut writer = lock(&self.writer);
+        let _ = writeln!(writer, "{event}").and_then(|()| writer.flush());
+    }
+}
+
+impl<W: Write + Send> Progress for JsonProgress<W> {
+    fn started(&self, id: TransferId, name: &str, total: Option<u64>) {
+        lock(&self.transfers).insert(
+            id,
+            JsonTransfer {
+                name: name.to_owned(),
+                sent: 0,
+                total,
+                written: None,
+            },
+        );
+        self.emit(
+            &serde_json::json!({ "event": "started", "id": id.0, "name": name, "total": total }),
+        );
+    }
+
+    fn sent(&self, id: TransferId, bytes: u64) {
+        let event = {
+            let mut transfers = lock(&self.transfers);
+            let Some(transfer) = transfers.get_mut(&id) else {
+                return;
+            };
+            transfer.sent += bytes;
+
+            let due = transfer
+                .written
+                .is_none_or(|x| x.elapsed() >= Self::SENT_INTERVAL);
+            if !due && Some(transfer.sent) != transfer.total {
+                return;
+            }
+            transfer.written = Some(Instant::now());
+
+            serde_json::json!({
+                "event": "sent",
+                "id": id.0,
+                "name": transfer.name,
+                "sent": transfer.sent,
+                "total": transfer.total,
+            })
+        };
+        self.emit(&event);
+    }
+
+    fn finished(&self, id: TransferId) {
+        let Some(transfer) = lock(&self.transfers).remove(&id) else {
+            return;
+        };
+        self.emit(&serde_json::json!({ "event": "finished", "id": id.0, "name": transfer.name }));
+    }
+
+    fn message(&self, message: &str) {
+        self.emit(&serde_json::json!({ "event": "message", "message": message }));
+    }
+}
+
+/// A transfer reported to a `Progress`, which is finished when dropped so failures are reported too.
+pub(crate) struct Transfer {
+    progress: Arc<dyn Progress>,
+    id: TransferId,
+}
+
+impl Transfer {
+    pub(crate) fn start(progress: &Arc<dyn Progress>, name: String, total: Option<u64>) -> Self {
+        let id = TransferId::next();
+        progress.started(id, &name, total);
+        Self {
+            progress: progress.clone(),
+            id,
+        }
+    }
+
+    pub(crate) const fn id(&self) -> TransferId {
+        self.id
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        self.progress.finished(self.id);
+    }
+}
+
+/// A poisoned lock only means another thread panicked while reporting, the data is still usable.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_progress_throttles_sent_events() {
+        let progress = JsonProgress::new(Vec::new());
+        let id = TransferId::next();
+
+        progress.started(id, "a/x.png", Some(10));
+        for _ in 0..10 {
+            progress.sent(id, 1);
+        }
+        progress.finished(id);
+
+        let output = String::from_utf8(lock(&progress.writer).clone()).unwrap();
+        let sent = output
+            .lines()
+            .filter(|x| x.contains(r#""event":"sent""#))
+            .collect::<Vec<_>>();
+
+        // the first chunk is reported right away, the ones after it only once the transfer completes
+        assert_eq!(sent.len(), 2, "{output}");
+        assert!(sent[1].contains(r#""sent":10"#));
+        assert!(output.ends_with(&format!(
+            "{{\"event\":\"finished\",\"id\":{},\"name\":\"a/x.png\"}}\n",
+            id.0
+        )));
+    }
+}
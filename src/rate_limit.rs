@@ -0,0 +1,78 @@
+use std::{
+    num::NonZeroU64,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A token bucket limiting the bytes per second sent by every upload it is given to.
+///
+/// Cloning a `RateLimit` shares the bucket, so the limit applies to all concurrent uploads together.
+/// Up to a second worth of bytes can be sent at once after being idle.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    bytes_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimit {
+    /// Creates a `RateLimit` of `bytes_per_second`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::num::NonZeroU64;
+    /// # use catbox::{endpoint::Endpoints, rate_limit::RateLimit, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let limit = RateLimit::new(NonZeroU64::new(2 * 1024 * 1024).unwrap());
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?.with_rate_limit(limit);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(bytes_per_second: NonZeroU64) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let bytes_per_second = bytes_per_second.get() as f64;
+
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                bytes_per_second,
+                tokens: bytes_per_second,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Waits until `bytes` may be sent.
+    ///
+    /// The bytes are taken from the bucket right away, even when it goes into debt,
+    /// so concurrent callers queue up behind each other instead of racing for the refill.
+    pub(crate) async fn acquire(&self, bytes: u64) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+
+            bucket.tokens = elapsed
+                .mul_add(bucket.bytes_per_second, bucket.tokens)
+                .min(bucket.bytes_per_second);
+            bucket.refilled_at = now;
+
+            #[allow(clippy::cast_precision_loss)]
+            let bytes = bytes as f64;
+            bucket.tokens -= bytes;
+
+            (bucket.tokens < 0.0)
+                .then(|| Duration::from_secs_f64(-bucket.tokens / bucket.bytes_per_second))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
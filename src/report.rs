@@ -0,0 +1,70 @@
+use std::{fmt, process::ExitCode};
+
+use color_eyre::eyre::eyre;
+
+/// Exit code used when some, but not all, files of a batch failed.
+pub const PARTIAL_FAILURE_EXIT_CODE: u8 = 2;
+
+/// The failures of a batch of files, each processed independently of the others.
+#[derive(Debug)]
+pub struct BatchReport {
+    total: usize,
+    failures: Vec<(String, String)>,
+}
+
+impl BatchReport {
+    pub const fn new(total: usize) -> Self {
+        Self {
+            total,
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn fail(&mut self, file: impl ToString, err: &impl fmt::Display) {
+        // errors such as `ErrorCode` span multiple lines, which would break the table
+        let err = err.to_string().lines().collect::<Vec<_>>().join(" ");
+        self.failures.push((file.to_string(), err));
+    }
+
+    /// Adds the failures of a later step on the same files, such as adding uploaded files to an album.
+    pub fn merge(&mut self, other: Self) {
+        self.failures.extend(other.failures);
+    }
+
+    /// Prints the failures as a table on stderr, then turns them into the outcome of `cbx`.
+    ///
+    /// Everything failing is an error, while a partial failure is `PARTIAL_FAILURE_EXIT_CODE`
+    /// so scripts can tell the two apart.
+    pub fn finish(self) -> color_eyre::Result<ExitCode> {
+        if self.failures.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        eprintln!("{self}");
+
+        if self.failures.len() >= self.total {
+            return Err(eyre!("All {} files failed", self.total));
+        }
+
+        Ok(ExitCode::from(PARTIAL_FAILURE_EXIT_CODE))
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .failures
+            .iter()
+            .map(|(file, _)| file.chars().count())
+            .chain([4])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{} of {} files failed:", self.failures.len(), self.total)?;
+        write!(f, "{:width$}  ERROR", "FILE")?;
+        for (file, err) in &self.failures {
+            write!(f, "\n{file:width$}  {err}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+use crate::progress::Progress;
+
+/// An error that may go away when the request is sent again.
+pub trait Retryable {
+    /// Whether the failed request is worth sending again, such as on a dropped connection or a 5xx.
+    fn is_retryable(&self) -> bool;
+}
+
+/// How often and how long to wait before a failed request is sent again.
+///
+/// The delay doubles on every attempt, starting at `base_delay` and capped at 30 seconds,
+/// with up to half of it randomized so concurrent uploads don't retry in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    retries: u32,
+    base_delay: Duration,
+}
+
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+impl Default for RetryPolicy {
+    /// Three retries, starting with a delay of half a second.
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(500))
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a `RetryPolicy` sending a request up to `retries` more times after the first failure.
+    pub const fn new(retries: u32, base_delay: Duration) -> Self {
+        Self {
+            retries,
+            base_delay,
+        }
+    }
+
+    /// A `RetryPolicy` that never sends a request again.
+    pub const fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// The delay before retry number `retry`, starting from 0.
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(MAX_DELAY);
+        let jitter = rand::thread_rng().gen_range(0.0..=0.5);
+        delay.mul_f64(1.0 - jitter)
+    }
+
+    /// Runs `attempt` until it succeeds, fails with an error that isn't retryable, or the retries run out.
+    ///
+    /// Every retry is announced to `progress` as a message mentioning `name`.
+    pub(crate) async fn run<T, E, F, Fut>(
+        &self,
+        progress: &dyn Progress,
+        name: &str,
+        mut attempt: F,
+    ) -> Result<T, E>
+    where
+        E: Retryable + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match attempt().await {
+                Err(err) if retry < self.retries && err.is_retryable() => {
+                    let delay = self.delay(retry);
+                    retry += 1;
+                    progress.message(&format!(
+                        "{name}: {err}, retrying in {:.1}s ({retry}/{})",
+                        delay.as_secs_f64(),
+                        self.retries
+                    ));
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether a request that failed without a response is worth sending again.
+pub(crate) fn is_retryable_request(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request() || err.is_body()
+}
+
+/// Whether a response with status `code` is worth sending again.
+pub(crate) fn is_retryable_status(code: StatusCode) -> bool {
+    code.is_server_error()
+        || code == StatusCode::TOO_MANY_REQUESTS
+        || code == StatusCode::REQUEST_TIMEOUT
+}
@@ -0,0 +1,71 @@
+use serde_json::{json, Value};
+
+use crate::{endpoint::Endpoints, profile::Profile};
+
+/// Session of a `User` persisted in the keyring between invocations,
+/// so that logging in and scraping the userhash is not repeated on every run.
+///
+/// A session only belongs to the username and server it was created for.
+///
+/// Persisting is best effort, a keyring that can't be accessed simply means nothing is cached.
+#[derive(Default, Clone)]
+pub struct SessionCache {
+    pub cookies: Option<String>,
+    pub user_hash: Option<String>,
+}
+
+impl SessionCache {
+    /// Loads the cached session of `username` on the server at `endpoints`,
+    /// a session cached for another user or another server is ignored.
+    pub fn load(profile: &Profile, username: &str, endpoints: &Endpoints) -> Option<Self> {
+        let json = profile.session_entry().ok()?.get_password().ok()?;
+
+        let value = serde_json::from_str::<Value>(&json).ok()?;
+
+        if value["username"].as_str()? != username
+            || value["base"].as_str()? != endpoints.base().as_str()
+        {
+            return None;
+        }
+
+        let field = |name: &str| value[name].as_str().map(ToOwned::to_owned);
+
+        Some(Self {
+            cookies: field("cookies"),
+            user_hash: field("user_hash"),
+        })
+    }
+
+    /// Stores the session of `username` on the server at `endpoints`, replacing whatever was cached before.
+    pub fn store(&self, profile: &Profile, username: &str, endpoints: &Endpoints) {
+        let json = json!({
+            "username": username,
+            "base": endpoints.base().as_str(),
+            "cookies": self.cookies,
+            "user_hash": self.user_hash,
+        });
+
+        if let Ok(entry) = profile.session_entry() {
+            let _ = entry.set_password(&json.to_string());
+        }
+    }
+
+    /// Loads the cached session of `username` on the server at `endpoints`, applies `f` to it and stores it back.
+    pub fn update(
+        profile: &Profile,
+        username: &str,
+        endpoints: &Endpoints,
+        f: impl FnOnce(&mut Self),
+    ) {
+        let mut cache = Self::load(profile, username, endpoints).unwrap_or_default();
+        f(&mut cache);
+        cache.store(profile, username, endpoints);
+    }
+
+    /// Removes the cached session, used when the credentials change.
+    pub fn clear(profile: &Profile) {
+        if let Ok(entry) = profile.session_entry() {
+            let _ = entry.delete_credential();
+        }
+    }
+}
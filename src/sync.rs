@@ -0,0 +1,601 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use catbox::{
+    limits::Limits,
+    upload::{UploadSource, Uploader},
+};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, ResultExt, Snafu};
+
+use crate::{
+    add_to_album, parse_album, preflight, report::BatchReport, upload_files,
+    walk::expand_directories, walk::WalkOptions, BatchOptions, MULTI_PROGRESS, USER_INSTANCE,
+};
+
+#[derive(Snafu, Debug)]
+pub enum SyncError {
+    #[snafu(display("`{}` is not a directory", path.display()))]
+    NotADirectory { path: PathBuf },
+    #[snafu(display("Fails to read `{}`", path.display()))]
+    ReadManifest { path: PathBuf, source: io::Error },
+    #[snafu(display("`{}` is not a valid sync manifest, delete it to upload every file again", path.display()))]
+    ParseManifest {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[snafu(display("Fails to write `{}`", path.display()))]
+    WriteManifest { path: PathBuf, source: io::Error },
+    #[snafu(display("Fails to read the metadata of `{}`", path.display()))]
+    Metadata { path: PathBuf, source: io::Error },
+}
+
+/// Name of the manifest kept in a synced directory.
+///
+/// Catbox renames every upload, so the manifest is what maps local files to the files they were uploaded as.
+pub const MANIFEST_FILE_NAME: &str = ".cbxsync.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Manifest {
+    /// Uploaded files by their path relative to the synced directory, with `/` separators.
+    files: BTreeMap<String, Entry>,
+}
+
+/// The size and modification time of a local file, in seconds since the unix epoch when the platform supports it.
+type Stamp = (u64, Option<u64>);
+
+/// A local file as it was when uploaded, a file that no longer matches is uploaded again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    url: Url,
+    size: u64,
+    /// Seconds since the unix epoch, when the platform supports it.
+    modified: Option<u64>,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> Result<Self, SyncError> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).context(ReadManifestSnafu { path }),
+        };
+
+        serde_json::from_str(&text).context(ParseManifestSnafu { path })
+    }
+
+    fn save(&self, dir: &Path) -> Result<(), SyncError> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+
+        let text = serde_json::to_string_pretty(self).expect("manifest is always serializable");
+
+        std::fs::write(&path, text).context(WriteManifestSnafu { path })
+    }
+
+    /// Forgets the files that are no longer in the synced directory, `keys` being the ones that are.
+    fn prune(&mut self, keys: &HashSet<String>) {
+        self.files.retain(|key, _| keys.contains(key));
+    }
+}
+
+/// What `cbx album sync` does to bring an album in line with a directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// Local files that were never uploaded, or changed since.
+    upload: Vec<PathBuf>,
+    /// Local files that are uploaded but missing from the album, with their slugs.
+    add: Vec<(PathBuf, String)>,
+    /// Slugs in the album without a local file, only filled with `--delete`.
+    remove: Vec<String>,
+}
+
+impl SyncPlan {
+    /// Works out the plan for the files in `dir` given with their current stamps.
+    ///
+    /// `in_album` and `uploaded` are the slugs in the album and in the account. A file is only taken as uploaded
+    /// while its manifest entry matches its stamp and the account still has it, otherwise it is uploaded again.
+    fn new(
+        dir: &Path,
+        manifest: &Manifest,
+        local: Vec<(PathBuf, Stamp)>,
+        in_album: &HashSet<String>,
+        uploaded: &HashSet<String>,
+        delete: bool,
+    ) -> Self {
+        let mut plan = Self::default();
+        let mut kept = HashSet::new();
+
+        for (path, stamp) in local {
+            match manifest.files.get(&relative_key(dir, &path)).and_then(|x| {
+                let slug = slug(&x.url)?;
+                ((x.size, x.modified) == stamp && uploaded.contains(&slug)).then_some(slug)
+            }) {
+                Some(slug) => {
+                    if !in_album.contains(&slug) {
+                        plan.add.push((path, slug.clone()));
+                    }
+                    kept.insert(slug);
+                }
+                None => plan.upload.push(path),
+            }
+        }
+
+        if delete {
+            plan.remove = in_album
+                .iter()
+                .filter(|x| !kept.contains(*x))
+                .cloned()
+                .collect();
+            plan.remove.sort();
+        }
+
+        plan
+    }
+
+    /// Leaves the slugs the uploads ended up at out of `remove`,
+    /// as a changed file may be answered with an earlier upload that is already in the album.
+    fn keep_uploaded(&mut self, uploaded: &HashSet<String>) {
+        self.remove.retain(|x| !uploaded.contains(x));
+    }
+
+    const fn len(&self) -> usize {
+        self.upload.len() + self.add.len() + self.remove.len()
+    }
+}
+
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.len() == 0 {
+            return f.write_str("Album is up to date");
+        }
+        let mut lines = Vec::with_capacity(self.len());
+        for path in &self.upload {
+            lines.push(format!("upload  {}", path.display()));
+        }
+        for (path, slug) in &self.add {
+            lines.push(format!("add     {} ({slug})", path.display()));
+        }
+        for slug in &self.remove {
+            lines.push(format!("remove  {slug}"));
+        }
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// Publishes the files in `dir` to `album`.
+///
+/// New and changed files are uploaded, files uploaded before are added back to the album if they are missing,
+/// and with `delete`, album entries without a local file are removed from the album(but not deleted).
+/// Files to upload are checked against `limits` first. With `dry_run`, the plan is printed, the files checked and nothing is changed.
+pub async fn sync_album(
+    album: String,
+    dir: PathBuf,
+    delete: bool,
+    dry_run: bool,
+    limits: &Limits,
+    options: BatchOptions,
+) -> color_eyre::Result<BatchReport> {
+    ensure!(dir.is_dir(), NotADirectorySnafu { path: &dir });
+
+    let user = USER_INSTANCE.get().await?;
+
+    let parsed_album = parse_album(user.endpoints(), &album);
+
+    let local = expand_directories(
+        vec![UploadSource::Path(dir.clone())],
+        &WalkOptions {
+            recursive: true,
+            ..WalkOptions::default()
+        },
+    )?
+    .into_iter()
+    .filter_map(|x| match x {
+        UploadSource::Path(path) => Some(path),
+        _ => None,
+    })
+    .map(|path| stamp(&path).map(|x| (path, x)))
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let mut manifest = Manifest::load(&dir)?;
+
+    let in_album = parsed_album
+        .fetch_files(USER_INSTANCE.progress())
+        .await?
+        .urls
+        .iter()
+        .filter_map(slug)
+        .collect::<HashSet<_>>();
+
+    let uploaded = user
+        .fetch_uploaded_files()
+        .await?
+        .iter()
+        .filter_map(slug)
+        .collect::<HashSet<_>>();
+
+    let keys = local
+        .iter()
+        .map(|(path, _)| relative_key(&dir, path))
+        .collect::<HashSet<_>>();
+
+    let mut plan = SyncPlan::new(&dir, &manifest, local, &in_album, &uploaded, delete);
+
+    let sources = plan
+        .upload
+        .iter()
+        .cloned()
+        .map(UploadSource::Path)
+        .collect::<Vec<_>>();
+
+    // the plan is shown even when some of its files would be refused, so they can be told apart
+    if dry_run {
+        MULTI_PROGRESS.suspend(|| println!("{plan}"));
+    }
+
+    preflight(limits, &sources)?;
+
+    if dry_run {
+        return Ok(BatchReport::new(0));
+    }
+
+    let mut report = BatchReport::new(plan.len());
+
+    let (uploads, upload_report) =
+        upload_files(&Uploader::User(user), sources, false, options).await?;
+
+    report.merge(upload_report);
+
+    let mut slugs = plan.add.drain(..).map(|(_, slug)| slug).collect::<Vec<_>>();
+
+    let mut uploaded = HashSet::with_capacity(uploads.len());
+
+    for (source, url) in uploads {
+        let UploadSource::Path(path) = source else {
+            continue;
+        };
+        let Ok(url) = Url::parse(&url) else {
+            continue;
+        };
+        let (size, modified) = stamp(&path)?;
+        if let Some(slug) = slug(&url) {
+            uploaded.insert(slug.clone());
+            slugs.push(slug);
+        }
+        manifest.files.insert(
+            relative_key(&dir, &path),
+            Entry {
+                url,
+                size,
+                modified,
+            },
+        );
+    }
+
+    manifest.prune(&keys);
+    manifest.save(&dir)?;
+
+    plan.keep_uploaded(&uploaded);
+
+    if !slugs.is_empty() {
+        report.merge(add_to_album(album, slugs, options).await?);
+    }
+
+    if !plan.remove.is_empty() {
+        match user.remove_from_album(&parsed_album, &plan.remove).await {
+            Ok(()) => {
+                for slug in &plan.remove {
+                    MULTI_PROGRESS.suspend(|| println!("Removed from album: {slug}"));
+                }
+            }
+            Err(err) if options.fail_fast => return Err(err.into()),
+            Err(err) => {
+                for slug in &plan.remove {
+                    report.fail(slug, &err);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn slug(url: &Url) -> Option<String> {
+    Some(url.path_segments()?.next_back()?.to_owned())
+}
+
+/// The path of `path` relative to `dir`, with `/` separators so manifests can move between platforms.
+fn relative_key(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The size and modification time of the file at `path`, used to tell whether it changed since its upload.
+fn stamp(path: &Path) -> Result<Stamp, SyncError> {
+    let metadata = std::fs::metadata(path).context(MetadataSnafu { path })?;
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs());
+
+    Ok((metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{body_string_contains, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const DIR: &str = "/photos";
+
+    fn entry(slug: &str, size: u64, modified: u64) -> Entry {
+        Entry {
+            url: Url::parse(&format!("https://files.catbox.moe/{slug}")).unwrap(),
+            size,
+            modified: Some(modified),
+        }
+    }
+
+    fn manifest(entries: &[(&str, Entry)]) -> Manifest {
+        Manifest {
+            files: entries
+                .iter()
+                .map(|(key, entry)| ((*key).to_owned(), entry.clone()))
+                .collect(),
+        }
+    }
+
+    fn local(key: &str, size: u64, modified: u64) -> (PathBuf, Stamp) {
+        (Path::new(DIR).join(key), (size, Some(modified)))
+    }
+
+    fn slugs(slugs: &[&str]) -> HashSet<String> {
+        slugs.iter().map(|&x| x.to_owned()).collect()
+    }
+
+    #[test]
+    fn uploads_files_that_changed_or_are_gone_from_the_account() {
+        let manifest = manifest(&[
+            ("same.png", entry("aaa111.png", 10, 100)),
+            ("resized.png", entry("bbb222.png", 10, 100)),
+            ("touched.png", entry("ccc333.png", 10, 100)),
+            ("deleted.png", entry("ddd444.png", 10, 100)),
+        ]);
+
+        let plan = SyncPlan::new(
+            Path::new(DIR),
+            &manifest,
+            vec![
+                local("same.png", 10, 100),
+                local("resized.png", 11, 100),
+                local("touched.png", 10, 101),
+                local("deleted.png", 10, 100),
+                local("new.png", 10, 100),
+            ],
+            &slugs(&["aaa111.png", "bbb222.png", "ccc333.png"]),
+            &slugs(&["aaa111.png", "bbb222.png", "ccc333.png"]),
+            false,
+        );
+
+        assert_eq!(
+            plan,
+            SyncPlan {
+                upload: ["resized.png", "touched.png", "deleted.png", "new.png"]
+                    .map(|x| Path::new(DIR).join(x))
+                    .into(),
+                add: Vec::new(),
+                remove: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn adds_uploaded_files_missing_from_the_album() {
+        let manifest = manifest(&[("a.png", entry("aaa111.png", 10, 100))]);
+
+        let plan = SyncPlan::new(
+            Path::new(DIR),
+            &manifest,
+            vec![local("a.png", 10, 100)],
+            &HashSet::new(),
+            &slugs(&["aaa111.png"]),
+            false,
+        );
+
+        assert_eq!(
+            plan.add,
+            [(Path::new(DIR).join("a.png"), "aaa111.png".to_owned())]
+        );
+        assert!(plan.upload.is_empty() && plan.remove.is_empty());
+    }
+
+    #[test]
+    fn removes_album_entries_without_a_local_file_only_with_delete() {
+        let manifest = manifest(&[("a.png", entry("aaa111.png", 10, 100))]);
+        let in_album = slugs(&["zzz999.png", "aaa111.png", "yyy888.png"]);
+
+        let plan = |delete| {
+            SyncPlan::new(
+                Path::new(DIR),
+                &manifest,
+                vec![local("a.png", 10, 100)],
+                &in_album,
+                &in_album,
+                delete,
+            )
+        };
+
+        assert_eq!(plan(false), SyncPlan::default());
+        assert_eq!(plan(true).remove, ["yyy888.png", "zzz999.png"]);
+    }
+
+    #[test]
+    fn keeps_album_entries_a_changed_file_is_uploaded_to_again() {
+        let manifest = manifest(&[("a.png", entry("aaa111.png", 10, 100))]);
+        let in_album = slugs(&["aaa111.png", "zzz999.png"]);
+
+        let mut plan = SyncPlan::new(
+            Path::new(DIR),
+            &manifest,
+            vec![local("a.png", 10, 101)],
+            &in_album,
+            &in_album,
+            true,
+        );
+
+        assert_eq!(plan.upload, [Path::new(DIR).join("a.png")]);
+        assert_eq!(plan.remove, ["aaa111.png", "zzz999.png"]);
+
+        // the touched file is identical to its earlier upload, so it is answered with the same slug
+        plan.keep_uploaded(&slugs(&["aaa111.png"]));
+
+        assert_eq!(plan.remove, ["zzz999.png"]);
+    }
+
+    #[test]
+    fn prunes_files_no_longer_in_the_directory() {
+        let mut manifest = manifest(&[
+            ("kept.png", entry("aaa111.png", 10, 100)),
+            ("gone.png", entry("bbb222.png", 10, 100)),
+        ]);
+
+        manifest.prune(&slugs(&["kept.png", "new.png"]));
+
+        assert_eq!(manifest.files.keys().collect::<Vec<_>>(), ["kept.png"]);
+    }
+
+    /// Runs a sync of an album holding `abc123.png` and `def456.mp4` against a mock server,
+    /// with a directory holding only the first of them and a manifest that still lists a removed file.
+    #[tokio::test]
+    async fn dry_run_changes_nothing_and_delete_removes_from_album() {
+        const SESSION_COOKIE: &str = "PHPSESSID=mock-session";
+
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/user/dologin.php"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("set-cookie", format!("{SESSION_COOKIE}; Path=/")),
+            )
+            .mount(&server)
+            .await;
+
+        for (page, html) in [
+            (
+                "/user/view.php",
+                r#"<div id="results"><a href="{0}/abc123.png" target="_blank">abc123.png</a><a href="{0}/def456.mp4" target="_blank">def456.mp4</a></div>"#,
+            ),
+            (
+                "/user/manage.php",
+                r#"<div class="notesmall"><p><b>Your userhash is:</b> 0123456789abcdef</p></div>"#,
+            ),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(page))
+                .and(header("cookie", SESSION_COOKIE))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_string(html.replace("{0}", &server.uri())),
+                )
+                .mount(&server)
+                .await;
+        }
+
+        Mock::given(method("GET"))
+            .and(path("/c/alb001"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"<div class="imagecontainer"><img src="{0}/abc123.png"><video src="{0}/def456.mp4"></video></div>"#,
+                server.uri()
+            )))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/user/api.php"))
+            .and(body_string_contains("reqtype=removefromalbum"))
+            .and(body_string_contains("files=def456.mp4"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let endpoints =
+            catbox::endpoint::Endpoints::new(Url::parse(&server.uri()).unwrap()).unwrap();
+        USER_INSTANCE.set_user(
+            catbox::user::User::with_login(endpoints.clone(), "kyle", "hunter2").unwrap(),
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        std::fs::write(dir.join("cat.png"), "png bytes").unwrap();
+
+        let (size, modified) = stamp(&dir.join("cat.png")).unwrap();
+        manifest(&[
+            (
+                "cat.png",
+                Entry {
+                    url: endpoints.file("abc123.png"),
+                    size,
+                    modified,
+                },
+            ),
+            ("gone.png", entry("bbb222.png", 10, 100)),
+        ])
+        .save(&dir)
+        .unwrap();
+
+        let limits = Limits::catbox();
+        let sync = |dry_run| {
+            sync_album(
+                format!("{}/c/alb001", server.uri()),
+                dir.clone(),
+                true,
+                dry_run,
+                &limits,
+                BatchOptions {
+                    jobs: 1,
+                    fail_fast: true,
+                },
+            )
+        };
+
+        sync(true).await.expect("dry run");
+
+        let posts = server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| x.url.path() == "/user/api.php")
+            .count();
+        assert_eq!(posts, 0);
+        assert!(Manifest::load(&dir).unwrap().files.contains_key("gone.png"));
+
+        sync(false).await.expect("sync");
+
+        assert_eq!(
+            Manifest::load(&dir)
+                .unwrap()
+                .files
+                .keys()
+                .collect::<Vec<_>>(),
+            ["cat.png"]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use std::{convert::Infallible, fmt, path::PathBuf, str::FromStr};
+
+use reqwest::Url;
+use snafu::Snafu;
+
+use crate::{
+    anonymous::{Anonymous, AnonymousError},
+    litterbox::{Expiry, Litterbox, LitterboxError},
+    user::{User, UserError},
+};
+
+#[derive(Snafu, Debug)]
+pub enum UploadError {
+    #[snafu(transparent)]
+    User { source: UserError },
+    #[snafu(transparent)]
+    Anonymous { source: AnonymousError },
+    #[snafu(transparent)]
+    Litterbox { source: LitterboxError },
+    #[snafu(display("Litterbox does not support uploading from urls: {url}"))]
+    UrlUnsupported { url: Url },
+}
+
+/// The file name stdin is uploaded as, unless another one is given.
+pub const DEFAULT_STDIN_FILE_NAME: &str = "stdin";
+
+/// Something that can be uploaded, arguments starting with `http://` or `https://` are treated as urls,
+/// and `-` as stdin.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum UploadSource {
+    Path(PathBuf),
+    Url(Url),
+    /// Everything read from stdin, uploaded as a file named `file_name`.
+    Stdin {
+        file_name: String,
+    },
+}
+
+impl FromStr for UploadSource {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            return Ok(Self::Stdin {
+                file_name: DEFAULT_STDIN_FILE_NAME.to_owned(),
+            });
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            if let Ok(url) = Url::parse(s) {
+                return Ok(Self::Url(url));
+            }
+        }
+        Ok(Self::Path(PathBuf::from(s)))
+    }
+}
+
+impl fmt::Display for UploadSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Url(url) => write!(f, "{url}"),
+            Self::Stdin { .. } => f.write_str("<stdin>"),
+        }
+    }
+}
+
+/// Where uploaded files end up.
+pub enum Uploader<'a> {
+    /// Uploaded permanently to the account of the logged in `User`.
+    User(&'a User),
+    /// Uploaded anonymously to catbox, not tied to any account.
+    Anonymous(Anonymous),
+    /// Uploaded anonymously to litterbox, deleted after the given expiry.
+    Litterbox(Litterbox, Expiry),
+}
+
+impl Uploader<'_> {
+    /// Uploads `source`, returning the url of the uploaded file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::{anonymous::Anonymous, endpoint::Endpoints, upload::{UploadSource, Uploader}};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let uploader = Uploader::Anonymous(Anonymous::new(Endpoints::default())?);
+    /// let url = uploader.upload(&"./happy.mp4".parse()?).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload(&self, source: &UploadSource) -> Result<String, UploadError> {
+        match (self, source) {
+            (Self::User(user), UploadSource::Path(path)) => Ok(user.upload_file(path).await?),
+            (Self::User(user), UploadSource::Url(url)) => Ok(user.upload_url(url.clone()).await?),
+            (Self::User(user), UploadSource::Stdin { file_name }) => {
+                Ok(user.upload_reader(tokio::io::stdin(), file_name).await?)
+            }
+            (Self::Anonymous(anonymous), UploadSource::Path(path)) => {
+                Ok(anonymous.upload_file(path).await?)
+            }
+            (Self::Anonymous(anonymous), UploadSource::Url(url)) => {
+                Ok(anonymous.upload_url(url.clone()).await?)
+            }
+            (Self::Anonymous(anonymous), UploadSource::Stdin { file_name }) => Ok(anonymous
+                .upload_reader(tokio::io::stdin(), file_name)
+                .await?),
+            (Self::Litterbox(litterbox, expiry), UploadSource::Path(path)) => {
+                Ok(litterbox.upload_file(path, *expiry).await?)
+            }
+            (Self::Litterbox(litterbox, expiry), UploadSource::Stdin { file_name }) => {
+                Ok(litterbox
+                    .upload_reader(tokio::io::stdin(), file_name, *expiry)
+                    .await?)
+            }
+            (Self::Litterbox(..), UploadSource::Url(url)) => {
+                UrlUnsupportedSnafu { url: url.clone() }.fail()
+            }
+        }
+    }
+}
@@ -1,56 +1,91 @@
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use reqwest::{
-    multipart::{self, Part},
-    Body, StatusCode, Url,
-};
+use reqwest::Url;
 
-use futures_util::TryStreamExt;
 use snafu::prelude::*;
-use std::{
-    io,
-    path::{Path, PathBuf},
-    time::Duration,
+use std::{fmt, path::Path, sync::Arc};
+use tokio::{
+    io::AsyncRead,
+    sync::{Mutex, OnceCell},
 };
-use tokio::{fs::File, sync::OnceCell};
 
 use tl::ParserOptions;
-use tokio_util::codec::{BytesCodec, FramedRead};
 
 use crate::{
     album::Album,
     authentication::{AuthenticatedClient, AuthenticationError},
-    get_password_entry, get_username_entry, MULTI_PROGRESS,
+    endpoint::Endpoints,
+    network::{ApiClient, ApiError},
+    progress::{Progress, Transfer},
+    rate_limit::RateLimit,
+    retry::{RetryPolicy, Retryable},
 };
+#[cfg(feature = "keyring")]
+use crate::{profile::Profile, session::SessionCache};
+
+/// Username and password used for logging into the catbox website.
+#[derive(Clone)]
+struct Login {
+    username: String,
+    password: String,
+}
+
+impl fmt::Debug for Login {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Login")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
 
 #[derive(Clone)]
 pub struct User {
-    client: AuthenticatedClient,
+    api: ApiClient,
+    endpoints: Endpoints,
+    /// Where the session is cached, only set for a `User` created from a profile.
+    #[cfg(feature = "keyring")]
+    profile: Option<Profile>,
+    login: Option<Login>,
+    session: Arc<Mutex<Option<AuthenticatedClient>>>,
     user_hash: OnceCell<String>,
 }
 
+impl fmt::Debug for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("User");
+        debug.field("endpoints", &self.endpoints);
+        #[cfg(feature = "keyring")]
+        debug.field("profile", &self.profile);
+        debug
+            .field("login", &self.login)
+            .field("user_hash", &self.user_hash.get().map(|_| REDACTED))
+            .finish_non_exhaustive()
+    }
+}
+
+const REDACTED: &str = "<redacted>";
+
 #[derive(Snafu, Debug)]
 pub enum UserError {
-    #[snafu(display("Fails to create authenticated client with {username} and {password}"))]
-    AuthenticatedClientCreation {
-        username: String,
-        password: String,
-        source: AuthenticationError,
-    },
+    #[snafu(display(
+        "Fails to log in, please check your username and password with `cbx config save`"
+    ))]
+    AuthenticatedClientCreation { source: AuthenticationError },
+    #[cfg(feature = "keyring")]
     #[snafu(display("Fails to initilize keyring instance."))]
     KeyringInitilization { source: keyring::Error },
+    #[cfg(feature = "keyring")]
     #[snafu(display("Lack of password, please set one with `cbx config save --password`!"))]
     LackOfPassword { source: keyring::Error },
-    #[snafu(display("Lack of user, please set one with `cbx config save --username`!"))]
+    #[cfg(feature = "keyring")]
+    #[snafu(display("Lack of user, please set one with `cbx config save --username`, or set a userhash with `cbx config save --userhash`!"))]
     LackOfUser { source: keyring::Error },
+    #[snafu(display("This operation needs a logged in session, please set your username and password with `cbx config save --username --password`!"))]
+    LackOfLogin,
+    #[snafu(display("Fails to create reqwest client"))]
+    ClientCreation { source: reqwest::Error },
 
-    #[snafu(display("Request to target failed. url: '{url}'"))]
-    Request { url: String, source: reqwest::Error },
-    #[snafu(display("Return to response can't be parsed as text"))]
-    NotAText { source: reqwest::Error },
-    #[snafu(display("Request returns non 200 error code: '{}'!\nserver reason: {reason}", code.as_u16()))]
-    ErrorCode { reason: String, code: StatusCode },
-    #[snafu(display("Fails to read file `{}`", file.display()))]
-    ReadFile { file: PathBuf, source: io::Error },
+    #[snafu(transparent)]
+    Api { source: ApiError },
     #[snafu(display("Slug({slug}) given can not be found in user profile"))]
     InvalidSlug { slug: String },
 
@@ -72,195 +107,556 @@ pub enum UserError {
 
     #[snafu(display("Fails to parse a short from url: {url}"))]
     ShortParsing { url: Url },
+    #[snafu(display("Server returned an invalid album url: {text}"))]
+    InvalidAlbumUrl {
+        text: String,
+        source: url::ParseError,
+    },
 }
 
-const API_URL: &str = "https://catbox.moe/user/api.php";
+impl UserError {
+    /// Whether the error is caused by no credentials being configured at all(or the keyring not being accessible).
+    ///
+    /// A username without a password is not, since that is a half finished setup rather than a choice.
+    #[cfg(feature = "keyring")]
+    pub const fn is_missing_credentials(&self) -> bool {
+        matches!(
+            self,
+            Self::KeyringInitilization { .. } | Self::LackOfUser { .. }
+        )
+    }
+}
+
+impl Retryable for UserError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::Api { source } if source.is_retryable())
+    }
+}
+
+#[cfg(feature = "keyring")]
+const USER_HASH_ENV: &str = "CATBOX_USER_HASH";
 
 impl User {
-    /// Creates a new `User` instance.
+    /// Creates a new `User` instance from the credentials stored in `profile`, talking to the server at `endpoints`.
+    ///
+    /// The userhash is read from `CATBOX_USER_HASH`, falling back to the keyring.
+    /// The username and password are read from the keyring, and are only optional when a userhash is present.
     ///
-    /// The `User` struct stores the authenticated client that provides functions.
+    /// Logging in is deferred until an operation that scrapes the website is requested,
+    /// so a `User` with only a userhash never logs in.
+    /// The session cookies and scraped userhash are cached in the keyring, and reused by later instances.
     ///
     /// # Example
     ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, profile::Profile, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::new(&Profile::default(), Endpoints::default()).await?;
+    /// # Ok(())
+    /// # }
     /// ```
-    /// let user = User::new("kyle", "some_password");
+    #[cfg(feature = "keyring")]
+    pub async fn new(profile: &Profile, endpoints: Endpoints) -> Result<Self, UserError> {
+        let user_hash = std::env::var(USER_HASH_ENV)
+            .ok()
+            .filter(|x| !x.is_empty())
+            .or_else(|| profile.userhash_entry().ok()?.get_password().ok());
+
+        let login = match Self::read_login(profile) {
+            Ok(login) => Some(login),
+            Err(_) if user_hash.is_some() => None,
+            Err(err) => return Err(err),
+        };
+
+        let cache = login
+            .as_ref()
+            .and_then(|x| SessionCache::load(profile, &x.username, &endpoints))
+            .unwrap_or_default();
+
+        let session = cache
+            .cookies
+            .and_then(|x| AuthenticatedClient::from_cookies(&endpoints, &x).ok());
+
+        let mut user = Self::build(endpoints, login, user_hash.or(cache.user_hash), session)?;
+        user.profile = Some(profile.clone());
+
+        Ok(user)
+    }
+
+    /// Creates a new `User` instance from a username and password, talking to the server at `endpoints`.
+    ///
+    /// Unlike `User::new`, nothing is read from or cached in the keyring.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_login(Endpoints::default(), "kyle", "some_password")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_login(
+        endpoints: Endpoints,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<Self, UserError> {
+        let login = Login {
+            username: username.into(),
+            password: password.into(),
+        };
+        Self::build(endpoints, Some(login), None, None)
+    }
+
+    /// Creates a new `User` instance from a userhash, talking to the server at `endpoints`.
+    ///
+    /// A `User` created this way can't scrape the website, so listing files and albums fails with `LackOfLogin`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// # Ok(())
+    /// # }
     /// ```
-    pub async fn new() -> Result<Self, UserError> {
-        let username = get_username_entry()
+    pub fn with_user_hash(
+        endpoints: Endpoints,
+        user_hash: impl Into<String>,
+    ) -> Result<Self, UserError> {
+        Self::build(endpoints, None, Some(user_hash.into()), None)
+    }
+
+    fn build(
+        endpoints: Endpoints,
+        login: Option<Login>,
+        user_hash: Option<String>,
+        session: Option<AuthenticatedClient>,
+    ) -> Result<Self, UserError> {
+        let api = ApiClient::new().context(ClientCreationSnafu)?;
+
+        Ok(Self {
+            api,
+            endpoints,
+            #[cfg(feature = "keyring")]
+            profile: None,
+            login,
+            session: Arc::new(Mutex::new(session)),
+            user_hash: OnceCell::new_with(user_hash),
+        })
+    }
+
+    /// Reports the progress of uploads and logging in to `progress`, nothing is reported by default.
+    #[must_use]
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.api.progress = progress;
+        self
+    }
+
+    /// Sets how failed uploads are retried, `RetryPolicy::default()` is used otherwise.
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.api.retry = retry;
+        self
+    }
+
+    /// Limits how fast files are uploaded, the limit is shared with every other client given the same `RateLimit`.
+    #[must_use]
+    pub fn with_rate_limit(mut self, rate_limit: impl Into<Option<RateLimit>>) -> Self {
+        self.api.rate_limit = rate_limit.into();
+        self
+    }
+
+    /// Gets the server a `User` talks to.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Whether the `User` has a username and password, without them only the api can be used, not the scraped pages.
+    pub const fn has_login(&self) -> bool {
+        self.login.is_some()
+    }
+
+    #[cfg(feature = "keyring")]
+    fn read_login(profile: &Profile) -> Result<Login, UserError> {
+        let username = profile
+            .username_entry()
             .context(KeyringInitilizationSnafu)?
             .get_password()
             .context(LackOfUserSnafu)?;
-        let password = get_password_entry()
+        let password = profile
+            .password_entry()
             .context(KeyringInitilizationSnafu)?
             .get_password()
             .context(LackOfPasswordSnafu)?;
+        Ok(Login { username, password })
+    }
+
+    /// Updates the session cached for the profile the `User` was created from, if any.
+    #[cfg(feature = "keyring")]
+    fn update_cache(&self, f: impl FnOnce(&mut SessionCache)) {
+        if let (Some(profile), Some(login)) = (&self.profile, &self.login) {
+            SessionCache::update(profile, &login.username, &self.endpoints, f);
+        }
+    }
 
-        let progress = ProgressBar::new_spinner();
+    /// Gets the logged in session of a `User`, logging in on first use.
+    async fn session(&self) -> Result<AuthenticatedClient, UserError> {
+        let mut session = self.session.lock().await;
 
-        progress.enable_steady_tick(Duration::from_millis(200));
+        if let Some(client) = &*session {
+            return Ok(client.clone());
+        }
+
+        let client = self.login().await?;
+
+        *session = Some(client.clone());
+
+        Ok(client)
+    }
 
-        progress.set_message("Initilizing user...");
+    /// Logs in again, replacing the current session of a `User`.
+    async fn relogin(&self) -> Result<AuthenticatedClient, UserError> {
+        let mut session = self.session.lock().await;
 
-        let client = AuthenticatedClient::new(&username, &password)
+        let client = self.login().await?;
+
+        *session = Some(client.clone());
+
+        Ok(client)
+    }
+
+    async fn login(&self) -> Result<AuthenticatedClient, UserError> {
+        let login = self.login.as_ref().context(LackOfLoginSnafu)?;
+
+        let transfer = Transfer::start(&self.api.progress, "Logging in...".to_owned(), None);
+
+        let client = AuthenticatedClient::new(&self.endpoints, &login.username, &login.password)
             .await
-            .context(AuthenticatedClientCreationSnafu { username, password })?;
+            .context(AuthenticatedClientCreationSnafu)?;
 
-        progress.finish_and_clear();
+        drop(transfer);
 
-        Ok(Self {
-            client,
-            user_hash: OnceCell::new(),
-        })
+        #[cfg(feature = "keyring")]
+        self.update_cache(|x| x.cookies = client.cookies());
+
+        Ok(client)
+    }
+
+    /// Fetches the html of a page that requires being logged in, logging in again if the session has expired.
+    async fn fetch_html(&self, url: Url) -> Result<String, UserError> {
+        match self.session().await?.fetch_html(url.clone()).await {
+            Err(AuthenticationError::SessionExpired) => self
+                .relogin()
+                .await?
+                .fetch_html(url)
+                .await
+                .context(InvalidHtmlSnafu),
+            html => html.context(InvalidHtmlSnafu),
+        }
     }
 
     /// Uploads the file using `User`.
     ///
     /// # Example
     ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let url = user.upload_file("./happy.mp4").await?;
+    /// # Ok(())
+    /// # }
     /// ```
-    /// let user = User::new("kyle", "some_password");
-    /// user.upload_file("./happy.mp4").await?;
-    /// ```
+    pub async fn upload_file(&self, path: impl AsRef<Path> + Send) -> Result<String, UserError> {
+        let hash = self.get_user_hash().await?;
+
+        Ok(self
+            .api
+            .upload_file(&self.endpoints.api(), &[("userhash", &hash)], path.as_ref())
+            .await?)
+    }
+
+    /// Uploads everything read from `reader` as a file named `file_name`, such as the output of another program.
     ///
-    /// # Panics
+    /// The length is unknown up front so the upload is streamed, and it is never retried since `reader` can't be read again.
     ///
-    /// Panics when the template provided to `ProgressBar` is invalid(compile time mistake)
-    pub async fn upload_file(&self, path: impl AsRef<Path> + Send) -> Result<String, UserError> {
-        let path = path.as_ref();
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let url = user.upload_reader(tokio::io::stdin(), "dump.sql").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_reader(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        file_name: &str,
+    ) -> Result<String, UserError> {
+        let hash = self.get_user_hash().await?;
 
-        let file = File::open(path)
-            .await
-            .context(ReadFileSnafu { file: path })?;
+        Ok(self
+            .api
+            .upload_reader(
+                &self.endpoints.api(),
+                &[("userhash", &hash)],
+                reader,
+                file_name,
+            )
+            .await?)
+    }
 
-        let total_bytes = file
-            .metadata()
-            .await
-            .context(ReadFileSnafu { file: path })?
-            .len();
+    /// Uploads a file from a remote url using `User`, the file is fetched by catbox itself.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # use reqwest::Url;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let url = user.upload_url(Url::parse("https://example.com/happy.mp4")?).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn upload_url(&self, url: Url) -> Result<String, UserError> {
+        let hash = self.get_user_hash().await?;
 
-        let bar = ProgressBar::new(total_bytes).with_prefix(path.to_string_lossy().to_string());
+        Ok(self
+            .api
+            .upload_url(&self.endpoints.api(), &[("userhash", &hash)], &url)
+            .await?)
+    }
 
-        bar.set_style(
-            ProgressStyle::with_template(
-                "{prefix:.magenta}\n[ETA: {eta}] [{decimal_bytes_per_sec:}] [{elapsed_precise}] {wide_bar:.cyan/blue} {decimal_bytes}/{decimal_total_bytes}",
-            )
-            .expect("Invalid template(compile time issue)")
-            .progress_chars("##-"),
-        );
+    pub async fn upload_to_album(&self, album: &Album, slug: &str) -> Result<(), UserError> {
+        let user_hash = self.get_user_hash().await?;
 
-        MULTI_PROGRESS.add(bar.clone());
+        let short = album.short().context(ShortParsingSnafu {
+            url: album.url.clone(),
+        })?;
+
+        // checking requires scraping the website, so a userhash-only `User` leaves it to the api
+        if self.has_login() {
+            ensure!(
+                self.fetch_uploaded_files()
+                    .await?
+                    .into_iter()
+                    .any(|x| x.path_segments().and_then(Iterator::last) == Some(slug)),
+                InvalidSlugSnafu { slug }
+            );
+        }
 
-        bar.enable_steady_tick(Duration::from_millis(500));
+        let form = [
+            ("reqtype", "addtoalbum"),
+            ("userhash", &user_hash),
+            ("short", short),
+            ("files", slug),
+        ];
 
-        let bar_cloned = bar.clone();
+        self.api
+            .retry
+            .run(&*self.api.progress, slug, || self.post_api(&form))
+            .await?;
 
-        let stream = FramedRead::new(file, BytesCodec::new()).inspect_ok(move |x| {
-            bar_cloned.inc(x.len() as u64);
-        });
+        Ok(())
+    }
 
-        let body_stream = Body::wrap_stream(stream);
+    /// Removes files, given by their slugs, from an album.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{album::Album, endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let album = Album::from_short(user.endpoints(), "hpxdlu");
+    /// user.remove_from_album(&album, &["w0v6bk.webm"]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn remove_from_album(
+        &self,
+        album: &Album,
+        slugs: &[impl AsRef<str>],
+    ) -> Result<(), UserError> {
+        let user_hash = self.get_user_hash().await?;
 
-        let hash = self.get_user_hash().await?;
+        let short = album.short().context(ShortParsingSnafu {
+            url: album.url.clone(),
+        })?;
 
-        let form = multipart::Form::new()
-            .text("reqtype", "fileupload")
-            .text("userhash", hash)
-            .part(
-                "fileToUpload",
-                Part::stream_with_length(body_stream, total_bytes)
-                    .file_name(path.to_string_lossy().to_string()),
-            );
+        let files = join_slugs(slugs);
 
-        let resp = self
-            .client
-            .post(API_URL)
-            .multipart(form)
-            .send()
-            .await
-            .context(RequestSnafu {
-                url: path.to_string_lossy(),
-            })?;
+        self.post_api(&[
+            ("reqtype", "removefromalbum"),
+            ("userhash", &user_hash),
+            ("short", short),
+            ("files", &files),
+        ])
+        .await?;
 
-        let code = resp.status();
+        Ok(())
+    }
+
+    /// Deletes files, given by their slugs, from the account of a `User`.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// user.delete_files(&["w0v6bk.webm", "7mc3en.pdf"]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn delete_files(&self, slugs: &[impl AsRef<str>]) -> Result<(), UserError> {
+        let user_hash = self.get_user_hash().await?;
 
-        let text = resp.text().await.context(NotATextSnafu)?;
+        let files = join_slugs(slugs);
 
-        if !code.is_success() {
-            ErrorCodeSnafu {
-                code,
-                reason: &text,
-            }
-            .fail()?;
-        }
+        self.post_api(&[
+            ("reqtype", "deletefiles"),
+            ("userhash", &user_hash),
+            ("files", &files),
+        ])
+        .await?;
 
-        bar.finish_and_clear();
-        Ok(text)
+        Ok(())
     }
 
-    pub async fn upload_to_album(&self, album: &Album, slug: &str) -> Result<(), UserError> {
+    /// Creates a new album with the given files, returning the created `Album`.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let album = user.create_album("memes", "the good ones", &["w0v6bk.webm"]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create_album(
+        &self,
+        title: &str,
+        description: &str,
+        slugs: &[impl AsRef<str>],
+    ) -> Result<Album, UserError> {
         let user_hash = self.get_user_hash().await?;
 
-        let short = album
-            .url
-            .path_segments()
-            .context(ShortParsingSnafu {
-                url: album.url.clone(),
-            })?
-            .nth(1)
-            .context(ShortParsingSnafu {
-                url: album.url.clone(),
-            })?;
-
-        ensure!(
-            self.fetch_uploaded_files()
-                .await?
-                .into_iter()
-                .any(|x| &x.path()[1..] == slug),
-            InvalidSlugSnafu { slug }
-        );
-
-        let resp = self
-            .client
-            .post(API_URL)
-            .form(&[
-                ("reqtype", "addtoalbum"),
+        let files = join_slugs(slugs);
+
+        let text = self
+            .post_api(&[
+                ("reqtype", "createalbum"),
                 ("userhash", &user_hash),
-                ("short", short),
-                ("files", slug),
+                ("title", title),
+                ("desc", description),
+                ("files", &files),
             ])
-            .send()
-            .await
-            .context(RequestSnafu { url: API_URL })?;
+            .await?;
 
-        let code = resp.status();
+        let url = Url::parse(text.trim()).context(InvalidAlbumUrlSnafu { text: &text })?;
+
+        Ok(Album::new(url))
+    }
+
+    /// Edits an album, replacing its title, description and files.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{album::Album, endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// let album = Album::from_short(user.endpoints(), "hpxdlu");
+    /// user.edit_album(&album, "memes", "the better ones", &["7mc3en.pdf"]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn edit_album(
+        &self,
+        album: &Album,
+        title: &str,
+        description: &str,
+        slugs: &[impl AsRef<str>],
+    ) -> Result<(), UserError> {
+        let user_hash = self.get_user_hash().await?;
+
+        let short = album.short().context(ShortParsingSnafu {
+            url: album.url.clone(),
+        })?;
+
+        let files = join_slugs(slugs);
+
+        self.post_api(&[
+            ("reqtype", "editalbum"),
+            ("userhash", &user_hash),
+            ("short", short),
+            ("title", title),
+            ("desc", description),
+            ("files", &files),
+        ])
+        .await?;
 
-        if !code.is_success() {
-            ErrorCodeSnafu {
-                code,
-                reason: resp.text().await.context(NotATextSnafu)?,
-            }
-            .fail()?;
-        }
         Ok(())
     }
 
-    /// Gets the user hash of a `User`.
+    /// Deletes an album. The files inside the album are kept.
     ///
     /// # Example
     ///
+    /// ``` no_run
+    /// # use catbox::{album::Album, endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_user_hash(Endpoints::default(), "0123456789abcdef")?;
+    /// user.delete_album(&Album::from_short(user.endpoints(), "hpxdlu")).await?;
+    /// # Ok(())
+    /// # }
     /// ```
-    /// let user = User::new("kyle", "some_password");
+    pub async fn delete_album(&self, album: &Album) -> Result<(), UserError> {
+        let user_hash = self.get_user_hash().await?;
+
+        let short = album.short().context(ShortParsingSnafu {
+            url: album.url.clone(),
+        })?;
+
+        self.post_api(&[
+            ("reqtype", "deletealbum"),
+            ("userhash", &user_hash),
+            ("short", short),
+        ])
+        .await?;
+
+        Ok(())
+    }
+
+    /// Posts a form to the catbox api, returning the response text on success.
+    async fn post_api(&self, form: &[(&str, &str)]) -> Result<String, UserError> {
+        Ok(self.api.post_form(&self.endpoints.api(), form).await?)
+    }
+
+    /// Gets the user hash of a `User`.
+    ///
+    /// # Example
+    ///
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_login(Endpoints::default(), "kyle", "some_password")?;
+    /// let user_hash = user.get_user_hash().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn get_user_hash(&self) -> Result<String, UserError> {
-        const ACCOUNT_URL: &str = "https://catbox.moe/user/manage.php";
         self.user_hash
             .get_or_try_init(move || async move {
-                let html = self
-                    .client
-                    .fetch_html(ACCOUNT_URL)
-                    .await
-                    .context(InvalidHtmlSnafu)?;
+                let html = self.fetch_html(self.endpoints.account()).await?;
 
                 let html = tl::parse(&html, ParserOptions::default())
                     .context(HtmlParseSnafu { html: &html })?;
@@ -285,6 +681,9 @@ impl User {
                     .map(|x| x.trim_start().to_owned())
                     .context(LackOfUserHashSnafu)?;
 
+                #[cfg(feature = "keyring")]
+                self.update_cache(|x| x.user_hash = Some(user_hash.clone()));
+
                 Ok(user_hash)
             })
             .await
@@ -295,18 +694,16 @@ impl User {
     ///
     /// # Example
     ///
-    /// ```
-    /// let user = User::new("kyle", "some_password");
-    /// let albums = user.fetch_albums(&self).await?;
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_login(Endpoints::default(), "kyle", "some_password")?;
+    /// let albums = user.fetch_albums().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn fetch_albums(&self) -> Result<Vec<Album>, UserError> {
-        const ALBUM_VIEW_URL: &str = "https://catbox.moe/user/manage_albums.php";
-
-        let html = self
-            .client
-            .fetch_html(ALBUM_VIEW_URL)
-            .await
-            .context(InvalidHtmlSnafu)?;
+        let html = self.fetch_html(self.endpoints.album_view()).await?;
 
         let html =
             tl::parse(&html, ParserOptions::default()).context(HtmlParseSnafu { html: &html })?;
@@ -330,18 +727,16 @@ impl User {
     ///
     /// # Example
     ///
-    /// ```
-    /// let user = User::new("kyle", "some_password");
-    /// let albums = user.fetch_uploaded_files(&self).await?;
+    /// ``` no_run
+    /// # use catbox::{endpoint::Endpoints, user::User};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let user = User::with_login(Endpoints::default(), "kyle", "some_password")?;
+    /// let files = user.fetch_uploaded_files().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     pub async fn fetch_uploaded_files(&self) -> Result<Vec<Url>, UserError> {
-        const USER_VIEW_URL: &str = "https://catbox.moe/user/view.php";
-
-        let html = self
-            .client
-            .fetch_html(USER_VIEW_URL)
-            .await
-            .context(InvalidHtmlSnafu)?;
+        let html = self.fetch_html(self.endpoints.user_view()).await?;
 
         let html =
             tl::parse(&html, ParserOptions::default()).context(HtmlParseSnafu { html: &html })?;
@@ -364,3 +759,48 @@ impl User {
         Ok(files)
     }
 }
+
+/// Joins slugs into the space separated list the catbox api expects.
+fn join_slugs(slugs: &[impl AsRef<str>]) -> String {
+    slugs
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "hunter2-very-secret";
+    const USER_HASH: &str = "0123456789abcdef";
+
+    #[test]
+    fn debug_redacts_credentials() {
+        let login = Login {
+            username: "kyle".to_owned(),
+            password: PASSWORD.to_owned(),
+        };
+
+        let user = User {
+            api: ApiClient::new().unwrap(),
+            endpoints: Endpoints::default(),
+            #[cfg(feature = "keyring")]
+            profile: Some(Profile::default()),
+            login: Some(login.clone()),
+            session: Arc::new(Mutex::new(None)),
+            user_hash: OnceCell::new_with(Some(USER_HASH.to_owned())),
+        };
+
+        for rendered in [
+            format!("{login:?}"),
+            format!("{user:?}"),
+            format!("{user:#?}"),
+        ] {
+            assert!(rendered.contains("kyle"));
+            assert!(!rendered.contains(PASSWORD));
+            assert!(!rendered.contains(USER_HASH));
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use catbox::upload::UploadSource;
+use ignore::{gitignore::GitignoreBuilder, overrides::OverrideBuilder, WalkBuilder};
+use snafu::{ensure, ResultExt, Snafu};
+
+#[derive(Snafu, Debug)]
+pub enum WalkError {
+    #[snafu(display("`{}` is a directory, pass `--recursive` to upload the files inside it", path.display()))]
+    Directory { path: PathBuf },
+    #[snafu(display("Invalid glob `{glob}`"))]
+    Glob { glob: String, source: ignore::Error },
+    #[snafu(display("Fails to walk `{}`", path.display()))]
+    Walk {
+        path: PathBuf,
+        source: ignore::Error,
+    },
+}
+
+/// Name of the ignore file read in every directory being walked, on top of `.gitignore` and `.ignore`.
+pub const IGNORE_FILE_NAME: &str = ".cbxignore";
+
+/// Which files inside a directory are uploaded.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    pub recursive: bool,
+    /// Only files matching one of these globs are uploaded, every file is when it is empty.
+    pub include: Vec<String>,
+    /// Files matching one of these globs are skipped, along with everything inside a matching directory.
+    pub exclude: Vec<String>,
+}
+
+/// Replaces every directory in `sources` with the files inside it, in a stable order.
+///
+/// Files inside directories are listed relative to where `cbx` runs, so the printed urls can be mapped back to the tree.
+/// Hidden files and whatever `.gitignore`, `.ignore` or `.cbxignore` exclude are skipped, and `--include`/`--exclude`
+/// only narrow down what is left, an ignored file is never included back. Files given directly are always uploaded.
+pub fn expand_directories(
+    sources: Vec<UploadSource>,
+    options: &WalkOptions,
+) -> Result<Vec<UploadSource>, WalkError> {
+    let mut expanded = Vec::with_capacity(sources.len());
+
+    for source in sources {
+        match source {
+            UploadSource::Path(path) if path.is_dir() => {
+                ensure!(options.recursive, DirectorySnafu { path });
+                expanded.extend(walk(&path, options)?.into_iter().map(UploadSource::Path));
+            }
+            source => expanded.push(source),
+        }
+    }
+
+    Ok(expanded)
+}
+
+fn walk(root: &Path, options: &WalkOptions) -> Result<Vec<PathBuf>, WalkError> {
+    let mut includes = OverrideBuilder::new(root);
+
+    for glob in &options.include {
+        includes.add(glob).context(GlobSnafu { glob })?;
+    }
+
+    let includes = includes.build().context(WalkSnafu { path: root })?;
+
+    let mut excludes = GitignoreBuilder::new(root);
+
+    for glob in &options.exclude {
+        excludes.add_line(None, glob).context(GlobSnafu { glob })?;
+    }
+
+    let excludes = excludes.build().context(WalkSnafu { path: root })?;
+
+    // overrides given to the walker take precedence over ignore files, so they are matched on its output instead,
+    // while excluded directories are pruned on the way down like ignored ones
+    let mut files = WalkBuilder::new(root)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|x| x.is_dir());
+            entry.depth() == 0 || !excludes.matched(entry.path(), is_dir).is_ignore()
+        })
+        .build()
+        .filter_map(|entry| match entry {
+            Ok(entry) if entry.file_type().is_some_and(|x| x.is_file()) => {
+                (!includes.matched(entry.path(), false).is_ignore()).then(|| Ok(entry.into_path()))
+            }
+            Ok(_) => None,
+            Err(source) => Some(Err(source)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .context(WalkSnafu { path: root })?;
+
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_does_not_override_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        std::fs::write(root.join(IGNORE_FILE_NAME), "secret.log\n").unwrap();
+        for name in ["secret.log", "logs/app.log", "logs/app.txt"] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+
+        let files = walk(
+            root,
+            &WalkOptions {
+                recursive: true,
+                include: vec!["*.log".to_owned()],
+                exclude: Vec::new(),
+            },
+        );
+
+        assert_eq!(files.unwrap(), [root.join("logs/app.log")]);
+    }
+
+    #[test]
+    fn exclude_skips_whole_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for dir in ["node_modules/pkg", "build", "src/drafts"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for name in [
+            "node_modules/pkg/index.js",
+            "build/out.js",
+            "src/drafts/wip.js",
+            "src/main.js",
+        ] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+
+        let files = walk(
+            root,
+            &WalkOptions {
+                recursive: true,
+                include: Vec::new(),
+                exclude: vec![
+                    "node_modules".to_owned(),
+                    "build/".to_owned(),
+                    "drafts".to_owned(),
+                ],
+            },
+        );
+
+        assert_eq!(files.unwrap(), [root.join("src/main.js")]);
+    }
+}
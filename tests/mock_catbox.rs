@@ -0,0 +1,723 @@
+//! End to end tests against an in-process stand-in for catbox.
+//!
+//! `MockCatbox` serves the pages that are scraped(`manage.php`, `view.php`, `manage_albums.php`, `/c/<short>`)
+//! with the markup catbox currently uses, so a change to catbox's html shows up here as a failing parser.
+
+use std::{
+    num::NonZeroU64,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::Url;
+use tempfile::TempDir;
+use wiremock::{
+    matchers::{body_string_contains, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use catbox::{
+    album::Album,
+    anonymous::Anonymous,
+    download::{DownloadOutcome, Downloader},
+    endpoint::Endpoints,
+    progress::{NoProgress, Progress, TransferId},
+    rate_limit::RateLimit,
+    retry::RetryPolicy,
+    user::User,
+};
+
+const USERNAME: &str = "kyle";
+const PASSWORD: &str = "hunter2-very-secret";
+const USER_HASH: &str = "0123456789abcdef";
+const SESSION_COOKIE: &str = "PHPSESSID=mock-session";
+
+struct MockCatbox {
+    server: MockServer,
+}
+
+impl MockCatbox {
+    /// Starts a server that accepts `USERNAME` and `PASSWORD`, and only serves the scraped pages to the resulting session.
+    async fn start() -> Self {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/user/dologin.php"))
+            .and(body_string_contains(format!("username={USERNAME}")))
+            .and(body_string_contains(format!("password={PASSWORD}")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("set-cookie", format!("{SESSION_COOKIE}; Path=/")),
+            )
+            .mount(&server)
+            .await;
+
+        let mock = Self { server };
+
+        mock.mount_page(
+            "/user/manage.php",
+            format!(
+                r#"<html><body><div class="notesmall"><p><b>Your userhash is:</b> {USER_HASH}</p></div></body></html>"#
+            ),
+        )
+        .await;
+
+        mock.mount_page(
+            "/user/view.php",
+            format!(
+                r#"<html><body><div id="results"><a href="{0}/abc123.png" target="_blank">abc123.png</a><a href="{0}/def456.mp4" target="_blank">def456.mp4</a></div></body></html>"#,
+                mock.server.uri()
+            ),
+        )
+        .await;
+
+        mock.mount_page(
+            "/user/manage_albums.php",
+            format!(
+                r#"<html><body><span class="textHolder">{0}/c/alb001</span><span class="textHolder">{0}/c/alb002</span></body></html>"#,
+                mock.server.uri()
+            ),
+        )
+        .await;
+
+        Mock::given(method("GET"))
+            .and(path("/c/alb001"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"<html><body><div class="title"><h2>Cats &amp; dogs</h2><p>The &quot;good&quot; ones</p></div><div class="imagecontainer"><img src="{0}/abc123.png"><video src="{0}/def456.mp4"></video></div></body></html>"#,
+                mock.server.uri()
+            )))
+            .mount(&mock.server)
+            .await;
+
+        mock
+    }
+
+    /// Serves `html` at `page`, but only to requests carrying the session cookie.
+    async fn mount_page(&self, page: &str, html: String) {
+        Mock::given(method("GET"))
+            .and(path(page))
+            .and(header("cookie", SESSION_COOKIE))
+            .respond_with(ResponseTemplate::new(200).set_body_string(html))
+            .mount(&self.server)
+            .await;
+    }
+
+    fn endpoints(&self) -> Endpoints {
+        Endpoints::new(Url::parse(&self.server.uri()).expect("mock server uri is valid"))
+            .expect("mock server uri is a base")
+    }
+
+    fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("{}{path}", self.server.uri())).expect("mock url is valid")
+    }
+
+    /// How many times a login was attempted.
+    #[cfg(feature = "keyring")]
+    async fn logins(&self) -> usize {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|x| x.url.path() == "/user/dologin.php")
+            .count()
+    }
+
+    fn user(&self) -> User {
+        User::with_login(self.endpoints(), USERNAME, PASSWORD).expect("client creation")
+    }
+}
+
+/// Writes a file to upload into a temp directory of its own, which is removed once the returned guard is dropped.
+async fn temp_file(name: &str, contents: &str) -> (TempDir, PathBuf) {
+    let dir = TempDir::new().expect("temp dir is writable");
+    let path = dir.path().join(name);
+    tokio::fs::write(&path, contents)
+        .await
+        .expect("temp dir is writable");
+    (dir, path)
+}
+
+#[tokio::test]
+async fn scrapes_user_hash_after_logging_in() {
+    let mock = MockCatbox::start().await;
+
+    let user_hash = mock.user().get_user_hash().await.expect("user hash");
+
+    assert_eq!(user_hash, USER_HASH);
+}
+
+#[tokio::test]
+async fn lists_uploaded_files() {
+    let mock = MockCatbox::start().await;
+
+    let files = mock.user().fetch_uploaded_files().await.expect("files");
+
+    assert_eq!(files, [mock.url("/abc123.png"), mock.url("/def456.mp4")]);
+}
+
+#[tokio::test]
+async fn lists_albums() {
+    let mock = MockCatbox::start().await;
+
+    let albums = mock.user().fetch_albums().await.expect("albums");
+
+    assert_eq!(
+        albums,
+        [
+            Album::new(mock.url("/c/alb001")),
+            Album::new(mock.url("/c/alb002"))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lists_album_files() {
+    let mock = MockCatbox::start().await;
+
+    let files = Album::from_short(&mock.endpoints(), "alb001")
+        .fetch_files(&(Arc::new(NoProgress) as Arc<dyn Progress>))
+        .await
+        .expect("album files");
+
+    assert_eq!(
+        files.urls,
+        [mock.url("/abc123.png"), mock.url("/def456.mp4")]
+    );
+}
+
+#[tokio::test]
+async fn reads_album_details() {
+    let mock = MockCatbox::start().await;
+
+    let details = Album::from_short(&mock.endpoints(), "alb001")
+        .fetch_details(&(Arc::new(NoProgress) as Arc<dyn Progress>))
+        .await
+        .expect("album details");
+
+    assert_eq!(details.title, "Cats & dogs");
+    assert_eq!(details.description, r#"The "good" ones"#);
+    assert_eq!(details.files.urls.len(), 2);
+}
+
+#[tokio::test]
+async fn uploads_file_as_multipart() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains(r#"name="reqtype""#))
+        .and(body_string_contains("fileupload"))
+        .and(body_string_contains(r#"name="userhash""#))
+        .and(body_string_contains(USER_HASH))
+        .and(body_string_contains(r#"name="fileToUpload""#))
+        .and(body_string_contains("hello catbox"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/new001.txt")))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let (_dir, file) = temp_file("upload.txt", "hello catbox").await;
+
+    let url = mock.user().upload_file(&file).await.expect("upload");
+
+    assert_eq!(url, mock.url("/new001.txt").as_str());
+}
+
+/// Records the events reported to it, summing up the bytes sent.
+#[derive(Default)]
+struct RecordingProgress {
+    events: Mutex<Vec<String>>,
+    names: Mutex<std::collections::HashMap<TransferId, String>>,
+}
+
+impl Progress for RecordingProgress {
+    fn started(&self, id: TransferId, name: &str, total: Option<u64>) {
+        self.names.lock().unwrap().insert(id, name.to_owned());
+        let mut events = self.events.lock().unwrap();
+        events.push(format!("started {name} {total:?}"));
+        events.push("sent 0".to_owned());
+    }
+    fn sent(&self, _id: TransferId, bytes: u64) {
+        let mut events = self.events.lock().unwrap();
+        let sent = events
+            .pop()
+            .and_then(|x| x.strip_prefix("sent ")?.parse::<u64>().ok())
+            .expect("sent is only reported after started");
+        events.push(format!("sent {}", sent + bytes));
+    }
+    fn finished(&self, id: TransferId) {
+        let name = self.names.lock().unwrap().remove(&id).unwrap_or_default();
+        self.events.lock().unwrap().push(format!("finished {name}"));
+    }
+    fn message(&self, message: &str) {
+        self.events.lock().unwrap().push(message.to_owned());
+    }
+}
+
+#[tokio::test]
+async fn upload_reports_progress() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/new002.txt")))
+        .mount(&mock.server)
+        .await;
+
+    let (_dir, file) = temp_file("progress.txt", "hello catbox").await;
+
+    let progress = Arc::new(RecordingProgress::default());
+
+    User::with_user_hash(mock.endpoints(), USER_HASH)
+        .expect("client creation")
+        .with_progress(progress.clone())
+        .upload_file(&file)
+        .await
+        .expect("upload");
+
+    let name = file.to_string_lossy();
+
+    assert_eq!(
+        *progress.events.lock().unwrap(),
+        [
+            format!("started {name} Some(12)"),
+            "sent 12".to_owned(),
+            format!("finished {name}"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn upload_is_retried_on_server_errors() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .with_priority(1)
+        .mount(&mock.server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains("hello catbox"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/new003.txt")))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let (_dir, file) = temp_file("retry.txt", "hello catbox").await;
+
+    let url = User::with_user_hash(mock.endpoints(), USER_HASH)
+        .expect("client creation")
+        .with_retry(RetryPolicy::new(2, Duration::ZERO))
+        .upload_file(&file)
+        .await
+        .expect("upload after retries");
+
+    assert_eq!(url, mock.url("/new003.txt").as_str());
+}
+
+#[tokio::test]
+async fn upload_is_not_retried_on_client_errors() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .respond_with(ResponseTemplate::new(412).set_body_string("No file"))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let (_dir, file) = temp_file("no-retry.txt", "hello catbox").await;
+
+    let result = User::with_user_hash(mock.endpoints(), USER_HASH)
+        .expect("client creation")
+        .with_retry(RetryPolicy::new(2, Duration::ZERO))
+        .upload_file(&file)
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn rate_limit_is_shared_by_concurrent_uploads() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/new004.txt")))
+        .expect(2)
+        .mount(&mock.server)
+        .await;
+
+    let contents = "x".repeat(16 * 1024);
+    let (_first_dir, first) = temp_file("limited-1.txt", &contents).await;
+    let (_second_dir, second) = temp_file("limited-2.txt", &contents).await;
+
+    // the bucket starts with a second worth of bytes, the other 24 KiB take 3 seconds
+    let user = User::with_user_hash(mock.endpoints(), USER_HASH)
+        .expect("client creation")
+        .with_rate_limit(RateLimit::new(NonZeroU64::new(8 * 1024).unwrap()));
+
+    let start = Instant::now();
+
+    let (a, b) = tokio::join!(user.upload_file(&first), user.upload_file(&second));
+    a.expect("first upload");
+    b.expect("second upload");
+
+    assert!(start.elapsed() >= Duration::from_millis(2500));
+}
+
+#[tokio::test]
+async fn uploads_reader_without_length() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains(r#"filename="dump.sql""#))
+        .and(body_string_contains("CREATE TABLE files"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/new005.sql")))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let progress = Arc::new(RecordingProgress::default());
+
+    let url = User::with_user_hash(mock.endpoints(), USER_HASH)
+        .expect("client creation")
+        .with_progress(progress.clone())
+        .upload_reader(
+            std::io::Cursor::new(b"CREATE TABLE files();".to_vec()),
+            "dump.sql",
+        )
+        .await
+        .expect("upload");
+
+    assert_eq!(url, mock.url("/new005.sql").as_str());
+
+    assert_eq!(
+        *progress.events.lock().unwrap(),
+        ["started dump.sql None", "sent 21", "finished dump.sql"]
+    );
+}
+
+#[tokio::test]
+async fn adds_uploaded_file_to_album() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains("reqtype=addtoalbum"))
+        .and(body_string_contains(format!("userhash={USER_HASH}")))
+        .and(body_string_contains("short=alb001"))
+        .and(body_string_contains("files=abc123.png"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let album = Album::from_short(&mock.endpoints(), "alb001");
+
+    mock.user()
+        .upload_to_album(&album, "abc123.png")
+        .await
+        .expect("add to album");
+}
+
+#[tokio::test]
+async fn refuses_to_add_unknown_file_to_album() {
+    let mock = MockCatbox::start().await;
+
+    let album = Album::from_short(&mock.endpoints(), "alb001");
+
+    let result = mock.user().upload_to_album(&album, "unknown.png").await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn user_hash_only_never_logs_in() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/dologin.php"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .with_priority(1)
+        .mount(&mock.server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains("reqtype=deletefiles"))
+        .and(body_string_contains(format!("userhash={USER_HASH}")))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let user = User::with_user_hash(mock.endpoints(), USER_HASH).expect("client creation");
+
+    user.delete_files(&["abc123.png"]).await.expect("delete");
+
+    assert!(user.fetch_uploaded_files().await.is_err());
+}
+
+#[tokio::test]
+async fn logs_in_again_when_session_expires() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/user/view.php"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"<form action="dologin.php" method="post"></form>"#),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock.server)
+        .await;
+
+    let files = mock.user().fetch_uploaded_files().await.expect("files");
+
+    assert_eq!(files.len(), 2);
+}
+
+#[tokio::test]
+async fn failed_login_does_not_leak_credentials() {
+    const WRONG_PASSWORD: &str = "not-the-password";
+
+    let mock = MockCatbox::start().await;
+
+    let user = User::with_login(mock.endpoints(), USERNAME, WRONG_PASSWORD).expect("client");
+
+    let err = user.get_user_hash().await.expect_err("login fails");
+
+    let report = color_eyre::Report::from(err);
+
+    for rendered in [
+        format!("{report}"),
+        format!("{report:?}"),
+        format!("{report:#}"),
+    ] {
+        assert!(!rendered.contains(WRONG_PASSWORD));
+    }
+}
+
+#[tokio::test]
+async fn uploads_anonymously_without_user_hash() {
+    let mock = MockCatbox::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/user/api.php"))
+        .and(body_string_contains(r#"name="fileToUpload""#))
+        .respond_with(ResponseTemplate::new(200).set_body_string(mock.url("/anon01.txt")))
+        .expect(1)
+        .mount(&mock.server)
+        .await;
+
+    let (_dir, file) = temp_file("anonymous.txt", "hello anonymous").await;
+
+    let url = Anonymous::new(mock.endpoints())
+        .expect("client creation")
+        .upload_file(&file)
+        .await
+        .expect("upload");
+
+    assert_eq!(url, mock.url("/anon01.txt").as_str());
+
+    let requests = mock.server.received_requests().await.unwrap_or_default();
+    assert!(requests
+        .iter()
+        .all(|x| !String::from_utf8_lossy(&x.body).contains(r#"name="userhash""#)));
+}
+
+#[tokio::test]
+async fn downloads_album_and_skips_existing_files() {
+    let mock = MockCatbox::start().await;
+
+    for (file, body) in [("/abc123.png", "png bytes"), ("/def456.mp4", "mp4 bytes!")] {
+        Mock::given(path(file))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&mock.server)
+            .await;
+    }
+
+    let files = Album::from_short(&mock.endpoints(), "alb001")
+        .fetch_files(&(Arc::new(NoProgress) as Arc<dyn Progress>))
+        .await
+        .expect("album files");
+
+    // the directory is left to the downloader to create
+    let temp = TempDir::new().expect("temp dir is writable");
+    let dir = temp.path().join("download");
+
+    let downloader = Downloader::new().expect("client creation");
+
+    for url in &files.urls {
+        let (_, outcome) = downloader.download(url, &dir).await.expect("download");
+        assert_eq!(outcome, DownloadOutcome::Downloaded);
+    }
+
+    assert_eq!(
+        tokio::fs::read_to_string(dir.join("abc123.png"))
+            .await
+            .unwrap(),
+        "png bytes"
+    );
+    assert_eq!(
+        tokio::fs::read_to_string(dir.join("def456.mp4"))
+            .await
+            .unwrap(),
+        "mp4 bytes!"
+    );
+
+    let (_, outcome) = downloader
+        .download(&mock.endpoints().file("abc123.png"), &dir)
+        .await
+        .expect("second download");
+    assert_eq!(outcome, DownloadOutcome::Skipped);
+
+    // the existing file is compared against a `HEAD` request, the file itself is only fetched once
+    let fetches = mock
+        .server
+        .received_requests()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|x| x.method.as_str() == "GET" && x.url.path() == "/abc123.png")
+        .count();
+    assert_eq!(fetches, 1);
+
+    let mut entries = tokio::fs::read_dir(&dir).await.unwrap();
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        names.push(entry.file_name().into_string().unwrap());
+    }
+    names.sort();
+    assert_eq!(names, ["abc123.png", "def456.mp4"]);
+}
+
+#[cfg(feature = "keyring")]
+/// A keyring kept in memory, shared by every entry so the session cache persists between `User`s like it does on disk.
+#[derive(Default)]
+struct MemoryKeyring(Arc<Mutex<std::collections::HashMap<String, Vec<u8>>>>);
+
+#[cfg(feature = "keyring")]
+struct MemoryCredential {
+    store: Arc<Mutex<std::collections::HashMap<String, Vec<u8>>>>,
+    key: String,
+}
+
+#[cfg(feature = "keyring")]
+impl keyring::credential::CredentialApi for MemoryCredential {
+    fn set_password(&self, password: &str) -> keyring::Result<()> {
+        self.set_secret(password.as_bytes())
+    }
+
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        self.store
+            .lock()
+            .unwrap()
+            .insert(self.key.clone(), secret.to_owned());
+        Ok(())
+    }
+
+    fn get_password(&self) -> keyring::Result<String> {
+        String::from_utf8(self.get_secret()?)
+            .map_err(|x| keyring::Error::BadEncoding(x.into_bytes()))
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        self.store
+            .lock()
+            .unwrap()
+            .get(&self.key)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        self.store
+            .lock()
+            .unwrap()
+            .remove(&self.key)
+            .map(drop)
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl keyring::credential::CredentialBuilderApi for MemoryKeyring {
+    fn build(
+        &self,
+        _: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<keyring::credential::Credential>> {
+        Ok(Box::new(MemoryCredential {
+            store: self.0.clone(),
+            key: format!("{service}/{user}"),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(feature = "keyring")]
+#[tokio::test]
+async fn cached_session_is_not_reused_on_another_server() {
+    use catbox::profile::Profile;
+
+    keyring::set_default_credential_builder(Box::new(MemoryKeyring::default()));
+
+    let profile = Profile::new(format!("session-test-{}", std::process::id()));
+    profile
+        .username_entry()
+        .and_then(|x| x.set_password(USERNAME))
+        .expect("keyring is writable");
+    profile
+        .password_entry()
+        .and_then(|x| x.set_password(PASSWORD))
+        .expect("keyring is writable");
+
+    let first = MockCatbox::start().await;
+    let second = MockCatbox::start().await;
+
+    User::new(&profile, first.endpoints())
+        .await
+        .expect("client creation")
+        .fetch_uploaded_files()
+        .await
+        .expect("uploaded files");
+
+    // the session cached by the first server is reused as long as the server is the same
+    User::new(&profile, first.endpoints())
+        .await
+        .expect("client creation")
+        .fetch_uploaded_files()
+        .await
+        .expect("uploaded files");
+
+    assert_eq!(first.logins().await, 1);
+
+    User::new(&profile, second.endpoints())
+        .await
+        .expect("client creation")
+        .fetch_uploaded_files()
+        .await
+        .expect("uploaded files");
+
+    assert_eq!(second.logins().await, 1);
+}